@@ -0,0 +1,172 @@
+use falco_plugin::anyhow::{anyhow, bail, Error};
+use falco_plugin::schemars::JsonSchema;
+use falco_plugin::serde::{Deserialize, Serialize};
+
+/// Largest number of buckets, every stream counting values in each of them.
+const MAX_BUCKETS: usize = 4096;
+
+/// How the values are grouped into histogram buckets. Values below the first
+/// bound and above the last one get a bucket of their own.
+#[derive(JsonSchema, Deserialize, Serialize, Clone, Debug, PartialEq)]
+#[schemars(crate = "falco_plugin::schemars")]
+#[serde(crate = "falco_plugin::serde", tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum BucketsConfig {
+    /// `count` buckets `width` values wide, the first one starting at `start`.
+    Linear {
+        #[serde(default)]
+        start: u64,
+        #[schemars(range(min = 1))]
+        width: u64,
+        #[schemars(range(min = 1, max = 4096))]
+        count: usize,
+    },
+    /// `count` buckets, the first one starting at `start` and each bound
+    /// `factor` times the previous one.
+    Exponential {
+        #[schemars(range(min = 1))]
+        start: u64,
+        /// Must be greater than 1, which the schema can't express: 1 itself is rejected.
+        #[schemars(range(min = 1))]
+        factor: f64,
+        #[schemars(range(min = 1, max = 4096))]
+        count: usize,
+    },
+    /// Buckets between consecutive `bounds`, in increasing order.
+    Explicit { bounds: Vec<u64> },
+}
+
+/// Histogram buckets, each one covering the values from its lower bound
+/// up to the next bucket's, excluded.
+#[derive(Debug, PartialEq)]
+pub struct Buckets {
+    /// Lower bound of every bucket, starting with 0
+    bounds: Vec<u64>,
+}
+
+impl Buckets {
+    pub fn new(config: &BucketsConfig) -> Result<Self, Error> {
+        let count = match config {
+            BucketsConfig::Linear { count, .. } | BucketsConfig::Exponential { count, .. } => *count,
+            BucketsConfig::Explicit { bounds } => bounds.len(),
+        };
+        if count > MAX_BUCKETS {
+            bail!("too many buckets: {count}, the limit is {MAX_BUCKETS}");
+        }
+
+        let bounds = match *config {
+            BucketsConfig::Linear { start, width, count } => {
+                if width == 0 || count == 0 {
+                    bail!("linear buckets need a positive `width` and `count`");
+                }
+                (0..=count as u64)
+                    .map(|i| {
+                        i.checked_mul(width)
+                            .and_then(|offset| offset.checked_add(start))
+                            .ok_or_else(|| anyhow!("linear bucket bounds exceed the u64 range"))
+                    })
+                    .collect::<Result<Vec<_>, _>>()?
+            }
+            BucketsConfig::Exponential { start, factor, count } => {
+                if start == 0 || factor.is_nan() || factor <= 1.0 || count == 0 {
+                    bail!("exponential buckets need a positive `start` and `count`, and a `factor` above 1");
+                }
+                let bounds: Vec<f64> = (0..=count)
+                    .map(|i| (start as f64 * factor.powf(i as f64)).round())
+                    .collect();
+                if bounds.iter().any(|&bound| bound >= u64::MAX as f64) {
+                    bail!("exponential bucket bounds exceed the u64 range");
+                }
+                bounds.into_iter().map(|bound| bound as u64).collect()
+            }
+            BucketsConfig::Explicit { ref bounds } => bounds.clone(),
+        };
+
+        if bounds.is_empty() {
+            bail!("buckets need at least one bound");
+        }
+        if bounds.windows(2).any(|pair| pair[0] >= pair[1]) {
+            bail!("bucket bounds must be strictly increasing, got {bounds:?}");
+        }
+        // The values below the first bound get a bucket too
+        let bounds = match bounds.first() {
+            Some(0) => bounds,
+            _ => std::iter::once(0).chain(bounds).collect(),
+        };
+        Ok(Self { bounds })
+    }
+
+    /// Number of buckets.
+    pub fn count(&self) -> usize {
+        self.bounds.len()
+    }
+
+    /// Returns the index of the bucket `value` falls in.
+    pub fn index(&self, value: u64) -> usize {
+        // The first bound is 0, so every value is at least in the first bucket
+        self.bounds.partition_point(|&bound| bound <= value) - 1
+    }
+
+    /// Returns the lower bound of the bucket at `index`.
+    pub fn lower_bound(&self, index: usize) -> u64 {
+        self.bounds[index]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bounds(config: BucketsConfig) -> Vec<u64> {
+        Buckets::new(&config).unwrap().bounds
+    }
+
+    #[test]
+    fn linear() {
+        assert_eq!(
+            bounds(BucketsConfig::Linear { start: 10, width: 5, count: 3 }),
+            [0, 10, 15, 20, 25]
+        );
+        assert_eq!(bounds(BucketsConfig::Linear { start: 0, width: 1, count: 2 }), [0, 1, 2]);
+    }
+
+    #[test]
+    fn exponential() {
+        assert_eq!(
+            bounds(BucketsConfig::Exponential { start: 10, factor: 10.0, count: 3 }),
+            [0, 10, 100, 1000, 10000]
+        );
+        for factor in [1.0, 0.5, f64::NAN] {
+            assert!(Buckets::new(&BucketsConfig::Exponential { start: 1, factor, count: 1 }).is_err());
+        }
+        assert!(Buckets::new(&BucketsConfig::Exponential { start: 1, factor: 2.0, count: 64 }).is_err());
+    }
+
+    #[test]
+    fn explicit() {
+        assert_eq!(bounds(BucketsConfig::Explicit { bounds: vec![0, 7] }), [0, 7]);
+        for bounds in [vec![], vec![3, 3], vec![5, 2]] {
+            assert!(Buckets::new(&BucketsConfig::Explicit { bounds }).is_err());
+        }
+    }
+
+    #[test]
+    fn too_many() {
+        let linear = |count| BucketsConfig::Linear { start: 0, width: 1, count };
+        assert!(Buckets::new(&linear(MAX_BUCKETS)).is_ok());
+        assert!(Buckets::new(&linear(MAX_BUCKETS + 1)).is_err());
+        assert!(Buckets::new(&linear(usize::MAX)).is_err());
+
+        let exponential = BucketsConfig::Exponential { start: 1, factor: 1.5, count: usize::MAX };
+        assert!(Buckets::new(&exponential).is_err());
+    }
+
+    #[test]
+    fn index() {
+        let buckets = Buckets::new(&BucketsConfig::Explicit { bounds: vec![10, 20] }).unwrap();
+        assert_eq!(buckets.count(), 3);
+        for (value, index) in [(0, 0), (9, 0), (10, 1), (19, 1), (20, 2), (u64::MAX, 2)] {
+            assert_eq!(buckets.index(value), index, "{value}");
+        }
+        assert_eq!(buckets.lower_bound(1), 10);
+    }
+}
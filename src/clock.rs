@@ -0,0 +1,70 @@
+use falco_plugin::schemars::JsonSchema;
+use falco_plugin::serde::{Deserialize, Serialize};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+/// Which time the events are generated in.
+#[derive(JsonSchema, Deserialize, Serialize, Clone, Debug, Default, PartialEq)]
+#[schemars(crate = "falco_plugin::schemars")]
+#[serde(crate = "falco_plugin::serde", tag = "mode", rename_all = "snake_case", deny_unknown_fields)]
+pub enum ClockConfig {
+    /// Events are generated in real time.
+    #[default]
+    Wall,
+    /// Events are generated without waiting, stamped with simulated time
+    /// starting at `epoch_ns` nanoseconds since the Unix epoch, or at the
+    /// current time when omitted.
+    Virtual { epoch_ns: Option<u64> },
+}
+
+/// Event timestamps are nanoseconds since the Unix epoch.
+pub enum Clock {
+    /// Scheduling against the system clock would break whenever it gets adjusted, so the
+    /// current time is derived from the monotonic clock, anchored to the system clock
+    /// once at startup.
+    Wall { origin: Instant, origin_ns: u64 },
+    /// Simulated time only advances by the intervals between events, so every event
+    /// is due as soon as it is scheduled.
+    Virtual { epoch_ns: Option<u64> },
+}
+
+impl Clock {
+    pub fn new(config: &ClockConfig) -> Self {
+        match *config {
+            ClockConfig::Wall => Clock::Wall {
+                origin: Instant::now(),
+                origin_ns: system_now(),
+            },
+            ClockConfig::Virtual { epoch_ns } => Clock::Virtual { epoch_ns },
+        }
+    }
+
+    /// Current time in nanoseconds since the Unix epoch, if the clock follows real time.
+    pub fn now(&self) -> Option<u64> {
+        match self {
+            Clock::Wall { origin, origin_ns } => {
+                Some(origin_ns.saturating_add(origin.elapsed().as_nanos() as u64))
+            }
+            Clock::Virtual { .. } => None,
+        }
+    }
+
+    /// Timestamp of the first event of a capture.
+    pub fn start(&self) -> u64 {
+        match self {
+            Clock::Wall { .. } => self.now().unwrap_or_default(),
+            Clock::Virtual { epoch_ns } => epoch_ns.unwrap_or_else(system_now),
+        }
+    }
+
+    /// Tells whether the event scheduled at `ts` should be generated already.
+    pub fn is_due(&self, ts: u64) -> bool {
+        self.now().is_none_or(|now| ts <= now)
+    }
+}
+
+fn system_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos() as u64)
+        .unwrap_or(0)
+}
@@ -0,0 +1,550 @@
+use crate::bucket::{Buckets, BucketsConfig};
+use crate::clock::ClockConfig;
+use crate::distribution::{DistributionConfig, Sampler};
+use crate::format::Format;
+use crate::payload::Value;
+use crate::params::{OpenParams, Source};
+use crate::rate::{CatchUp, Rate, RateConfig};
+use crate::rng::{stream_seed, Algorithm, GenRng};
+use crate::stats::ChiSquareBins;
+use crate::value::ValueTypeConfig;
+use crate::window::{Window, WindowConfig};
+use falco_plugin::anyhow::{anyhow, bail, Context, Error};
+use falco_plugin::schemars::JsonSchema;
+use falco_plugin::serde::Deserialize;
+use rand::Rng;
+use std::collections::BTreeSet;
+use std::time::Duration;
+
+/// The plugin configuration. Unknown keys are rejected, so that a typo
+/// is reported instead of being silently ignored.
+#[derive(JsonSchema, Deserialize)]
+#[schemars(crate = "falco_plugin::schemars")]
+#[serde(crate = "falco_plugin::serde", deny_unknown_fields)]
+pub struct Config {
+    /// Defines the random generator range: values are generated
+    /// between `min` and `range`, excluded. Can't be used together with `max`.
+    /// Optional when every stream defines its own range.
+    #[schemars(range(min = 1))]
+    range: Option<u64>,
+
+    /// Defines the smallest generated value.
+    #[serde(default)]
+    min: u64,
+
+    /// Excludes `min` itself from the generated values.
+    #[serde(default)]
+    min_exclusive: bool,
+
+    /// Defines the largest generated value. Can't be used together with `range`.
+    max: Option<u64>,
+
+    /// Excludes `max` itself from the generated values. Can't be used together with `range`.
+    #[serde(default)]
+    max_exclusive: bool,
+
+    /// Defines the distribution of the generated values.
+    /// Uniform when omitted.
+    #[serde(default)]
+    distribution: DistributionConfig,
+
+    /// Defines the type of the generated values.
+    /// Unsigned integers when omitted.
+    #[serde(default)]
+    value_type: ValueTypeConfig,
+
+    /// Seeds the random generator so that the same configuration
+    /// always produces the same sequence of values.
+    seed: Option<u64>,
+
+    /// Selects the random generator algorithm.
+    #[serde(default)]
+    algorithm: Algorithm,
+
+    /// Defines how often events are generated.
+    /// Two events per second when omitted.
+    #[serde(default)]
+    rate: RateConfig,
+
+    /// Defines what happens when Falco falls behind schedule:
+    /// `burst` generates all the missed events at once, `skip` drops them,
+    /// `drift` drops them and restarts the schedule from the current time.
+    #[serde(default)]
+    catch_up: CatchUp,
+
+    /// Maximum number of events returned to Falco at once.
+    /// Each batch contains every event that is due, up to this many.
+    #[serde(default = "default_batch_size")]
+    #[schemars(range(min = 1))]
+    batch_size: usize,
+
+    /// Maximum time, in microseconds, spent filling a single batch.
+    #[schemars(range(min = 1))]
+    batch_time_budget_us: Option<u64>,
+
+    /// Ends the capture after this many events.
+    max_events: Option<u64>,
+
+    /// Ends the capture after this many seconds.
+    max_duration_secs: Option<u64>,
+
+    /// Defines whether events follow real time or simulated time.
+    #[serde(default)]
+    pub(crate) clock: ClockConfig,
+
+    /// Defines how events are rendered as text: `decimal`, `hex`, `json`, or a
+    /// template using the `{value}`, `{hex}`, `{seq}`, `{stream}` and `{type}` placeholders.
+    #[serde(default = "default_format")]
+    format: String,
+
+    /// Defines several named generator streams, interleaved by timestamp.
+    /// A single stream uses the top level settings when omitted.
+    #[serde(default)]
+    streams: Vec<StreamConfig>,
+
+    /// Defines the events the `gen.mean`, `gen.stddev`, `gen.min`, `gen.max` and
+    /// `gen.median` fields are computed over. The last 100 events when omitted.
+    #[serde(default)]
+    window: WindowConfig,
+
+    /// Defines how many events back `gen.lag` can look, up to 100000.
+    #[serde(default = "default_max_lag")]
+    #[schemars(range(max = 100000))]
+    max_lag: usize,
+
+    /// Defines the histogram buckets counted by `gen.bucket_count`.
+    buckets: Option<BucketsConfig>,
+
+    /// Keeps the exact count of every distinct value, used by `gen.count`, `gen.count_range`,
+    /// `gen.distinct`, `gen.chi2` and `gen.surprisal`. Its memory grows with the number of
+    /// distinct values, so it's best disabled for wide ranges.
+    #[serde(default = "default_exact_histogram")]
+    pub(crate) exact_histogram: bool,
+}
+
+fn default_batch_size() -> usize {
+    128
+}
+
+fn default_max_lag() -> usize {
+    100
+}
+
+fn default_exact_histogram() -> bool {
+    true
+}
+
+fn default_format() -> String {
+    String::from("decimal")
+}
+
+impl Config {
+    /// Resolves `range`, `min` and `max` into inclusive bounds.
+    fn bounds(&self, stream: &StreamConfig) -> Result<(u64, u64), Error> {
+        // A stream setting any upper bound doesn't inherit the top level ones,
+        // nor whether they are excluded
+        let (range, max, max_exclusive) = if stream.range.is_some() || stream.max.is_some() {
+            (stream.range, stream.max, stream.max_exclusive.unwrap_or_default())
+        } else {
+            (self.range, self.max, stream.max_exclusive.unwrap_or(self.max_exclusive))
+        };
+        let min = stream.min.unwrap_or(self.min);
+        let min_exclusive = stream.min_exclusive.unwrap_or(self.min_exclusive);
+
+        let max = match (range, max) {
+            (Some(_), Some(_)) => bail!("`range` and `max` can't be both set"),
+            (Some(_), None) if max_exclusive => {
+                bail!("`range` and `max_exclusive` can't be both set, `range` is always excluded")
+            }
+            (None, None) => bail!("either `range` or `max` must be set"),
+            (Some(range), None) => range
+                .checked_sub(1)
+                .ok_or_else(|| anyhow!("`range` must be greater than 0"))?,
+            (None, Some(max)) if max_exclusive => max
+                .checked_sub(1)
+                .ok_or_else(|| anyhow!("`max` must be greater than 0 when `max_exclusive` is set"))?,
+            (None, Some(max)) => max,
+        };
+        let min = if min_exclusive {
+            min.checked_add(1)
+                .ok_or_else(|| anyhow!("`min` must be less than {} when `min_exclusive` is set", u64::MAX))?
+        } else {
+            min
+        };
+        if min > max {
+            bail!("no value to generate between `min` ({min}) and the upper bound ({max})");
+        }
+        Ok((min, max))
+    }
+}
+
+/// A named generator stream. Every omitted setting is inherited
+/// from the top level of the configuration.
+#[derive(JsonSchema, Deserialize, Default)]
+#[schemars(crate = "falco_plugin::schemars")]
+#[serde(crate = "falco_plugin::serde", deny_unknown_fields)]
+pub struct StreamConfig {
+    /// Defines the name of the stream, extracted by `gen.stream`.
+    name: String,
+
+    /// Defines the random generator range of the stream.
+    #[schemars(range(min = 1))]
+    range: Option<u64>,
+
+    /// Defines the smallest value of the stream.
+    min: Option<u64>,
+
+    /// Excludes `min` itself from the values of the stream.
+    min_exclusive: Option<bool>,
+
+    /// Defines the largest value of the stream.
+    max: Option<u64>,
+
+    /// Excludes `max` itself from the values of the stream.
+    max_exclusive: Option<bool>,
+
+    /// Defines the distribution of the values of the stream.
+    distribution: Option<DistributionConfig>,
+
+    /// Defines the type of the values of the stream.
+    value_type: Option<ValueTypeConfig>,
+
+    /// Defines how often the stream generates events.
+    rate: Option<RateConfig>,
+}
+
+/// Largest `max_lag`, every stream keeping that many values.
+const MAX_LAG: usize = 100_000;
+
+/// Name of the stream generated when the configuration doesn't list any.
+const DEFAULT_STREAM: &str = "default";
+
+/// The generator settings in effect, derived from the [`Config`]
+/// and the `open()` parameters of the current capture.
+pub struct Settings {
+    /// The generator streams, interleaved by timestamp
+    pub streams: Vec<StreamSettings>,
+
+    /// Seed of the random number generator. When unset,
+    /// every capture produces a different sequence.
+    pub seed: Option<u64>,
+
+    /// Random number generator algorithm
+    pub algorithm: Algorithm,
+
+    /// What happens to the events that fell behind schedule
+    pub catch_up: CatchUp,
+
+    /// Maximum number of events returned by a single `next_batch` call
+    pub batch_size: usize,
+
+    /// Maximum time spent filling a single batch
+    pub batch_time_budget: Option<Duration>,
+
+    /// Number of events after which a capture ends
+    pub max_events: Option<u64>,
+
+    /// Length of a capture, measured in event time
+    pub max_duration: Option<Duration>,
+
+    /// How events are rendered as text
+    pub format: Format,
+
+    /// Recent events the sliding window statistics are computed over
+    pub window: Window,
+
+    /// Number of previous values kept for `gen.lag`
+    pub max_lag: usize,
+
+    /// Histogram buckets, if any
+    pub buckets: Option<Buckets>,
+
+    /// Whether the exact count of every value is kept
+    pub exact_histogram: bool,
+}
+
+/// The settings of a single generator stream.
+pub struct StreamSettings {
+    /// Name of the stream
+    pub name: String,
+
+    /// Smallest value that can be generated
+    pub min: u64,
+
+    /// Largest value that can be generated
+    pub max: u64,
+
+    /// Distribution the values are drawn from
+    pub distribution: DistributionConfig,
+
+    /// Sampler of `distribution`
+    pub sampler: Sampler,
+
+    /// Type of the generated values
+    pub value_type: ValueTypeConfig,
+
+    /// Bins of the chi-square test against `distribution`
+    pub chi_square_bins: ChiSquareBins,
+
+    /// Time between consecutive events
+    pub rate: Rate,
+}
+
+impl Settings {
+    pub fn new(config: &Config, params: &OpenParams) -> Result<Self, Error> {
+        if config.batch_size == 0 {
+            bail!("`batch_size` must be greater than 0");
+        }
+        if config.batch_time_budget_us == Some(0) {
+            bail!("`batch_time_budget_us` must be greater than 0");
+        }
+        if config.max_lag > MAX_LAG {
+            bail!("`max_lag` must be at most {MAX_LAG}, got {}", config.max_lag);
+        }
+        if config.streams.len() > usize::from(u16::MAX) + 1 {
+            bail!("too many streams: {}", config.streams.len());
+        }
+        let mut names = BTreeSet::new();
+        if let Some(stream) = config.streams.iter().find(|stream| !names.insert(&stream.name)) {
+            bail!("duplicate stream name {:?}", stream.name);
+        }
+
+        let default_stream = StreamConfig {
+            name: String::from(DEFAULT_STREAM),
+            ..Default::default()
+        };
+        let streams = match config.streams.as_slice() {
+            [] => std::slice::from_ref(&default_stream),
+            streams => streams,
+        };
+        let streams = streams
+            .iter()
+            .map(|stream| {
+                StreamSettings::new(config, stream, params)
+                    .with_context(|| format!("invalid stream {:?}", stream.name))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        if let Some(stream) = streams.iter().find(|stream| matches!(stream.rate, Rate::Unlimited)) {
+            // Simulated time only advances by the intervals between events, which are
+            // all zero, so every event would share the same timestamp
+            if matches!(config.clock, ClockConfig::Virtual { .. }) {
+                bail!("stream {:?} has an unlimited rate, which requires the wall clock", stream.name);
+            }
+            // A duration window keeps every event of its period, and there's no
+            // telling how many of them an unlimited stream generates
+            if matches!(config.window, WindowConfig::Duration { .. }) {
+                bail!("stream {:?} has an unlimited rate, which requires a window of events", stream.name);
+            }
+        }
+
+        Ok(Self {
+            streams,
+            seed: params.seed.or(config.seed),
+            algorithm: config.algorithm,
+            catch_up: config.catch_up,
+            batch_size: config.batch_size,
+            batch_time_budget: config.batch_time_budget_us.map(Duration::from_micros),
+            max_events: params.max_events.or(config.max_events),
+            max_duration: params
+                .max_duration_secs
+                .or(config.max_duration_secs)
+                .map(Duration::from_secs),
+            format: Format::parse(&config.format)?,
+            window: Window::new(&config.window)?,
+            max_lag: config.max_lag,
+            buckets: config.buckets.as_ref().map(Buckets::new).transpose()?,
+            exact_histogram: config.exact_histogram,
+        })
+    }
+
+    /// Tells whether both settings have the same streams, in the same order.
+    pub fn same_streams(&self, other: &Settings) -> bool {
+        self.streams
+            .iter()
+            .map(|stream| &stream.name)
+            .eq(other.streams.iter().map(|stream| &stream.name))
+    }
+
+    /// Creates the random number generator of a stream. Each stream derives
+    /// its seed from the configured one, the first stream using it as is.
+    pub fn stream_rng(&self, index: usize) -> GenRng {
+        let seed = self.seed.map(|seed| stream_seed(seed, index as u64));
+        GenRng::new(self.algorithm, seed)
+    }
+}
+
+impl StreamSettings {
+    /// The `open()` parameters override both the stream and the top level settings.
+    fn new(config: &Config, stream: &StreamConfig, params: &OpenParams) -> Result<Self, Error> {
+        let distribution = match &params.source {
+            Source::Distribution(distribution) => distribution,
+            Source::Configured | Source::Replay { .. } => {
+                stream.distribution.as_ref().unwrap_or(&config.distribution)
+            }
+        };
+        let rate = match params.events_per_second {
+            Some(events_per_second) => RateConfig::Fixed { events_per_second },
+            None => stream.rate.as_ref().unwrap_or(&config.rate).clone(),
+        };
+        let value_type = stream.value_type.as_ref().unwrap_or(&config.value_type);
+        value_type.validate()?;
+
+        let (min, max) = config.bounds(stream)?;
+        let min = params.min.unwrap_or(min);
+        let max = params.max.unwrap_or(max);
+        if min > max {
+            bail!("min ({min}) must not be greater than max ({max})");
+        }
+
+        Ok(Self {
+            name: stream.name.clone(),
+            min,
+            max,
+            sampler: Sampler::new(distribution)?,
+            chi_square_bins: ChiSquareBins::new(distribution, min, max),
+            distribution: distribution.clone(),
+            value_type: value_type.clone(),
+            rate: Rate::new(&rate)?,
+        })
+    }
+
+    /// Draws the next value.
+    pub fn draw<R: Rng + ?Sized>(&self, rng: &mut R) -> Value {
+        self.value_type.draw(&self.sampler, rng, self.min, self.max)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A configuration generating values up to `max`, everything else left to its default.
+    fn up_to(max: u64) -> Config {
+        Config {
+            range: None,
+            min: 0,
+            min_exclusive: false,
+            max: Some(max),
+            max_exclusive: false,
+            distribution: DistributionConfig::default(),
+            value_type: ValueTypeConfig::default(),
+            seed: None,
+            algorithm: Algorithm::default(),
+            rate: RateConfig::default(),
+            catch_up: CatchUp::default(),
+            batch_size: default_batch_size(),
+            batch_time_budget_us: None,
+            max_events: None,
+            max_duration_secs: None,
+            clock: ClockConfig::default(),
+            format: default_format(),
+            streams: Vec::new(),
+            window: WindowConfig::default(),
+            max_lag: default_max_lag(),
+            buckets: None,
+            exact_histogram: default_exact_histogram(),
+        }
+    }
+
+    fn bounds(config: &Config) -> Result<(u64, u64), Error> {
+        config.bounds(&StreamConfig::default())
+    }
+
+    #[test]
+    fn range() {
+        let config = Config { range: Some(10), max: None, ..up_to(0) };
+        assert_eq!(bounds(&config).unwrap(), (0, 9));
+
+        let config = Config { range: Some(0), max: None, ..up_to(0) };
+        assert!(bounds(&config).unwrap_err().to_string().contains("greater than 0"));
+    }
+
+    #[test]
+    fn upper_bound_required_once() {
+        let config = Config { range: Some(10), ..up_to(20) };
+        assert!(bounds(&config).unwrap_err().to_string().contains("can't be both set"));
+
+        let config = Config { max: None, ..up_to(0) };
+        assert!(bounds(&config).is_err());
+
+        let config = Config { range: Some(10), max: None, max_exclusive: true, ..up_to(0) };
+        assert!(bounds(&config).unwrap_err().to_string().contains("`max_exclusive`"));
+    }
+
+    #[test]
+    fn min_above_max() {
+        assert_eq!(bounds(&Config { min: 20, ..up_to(20) }).unwrap(), (20, 20));
+        assert!(bounds(&Config { min: 21, ..up_to(20) }).is_err());
+        assert!(bounds(&Config { min: 20, min_exclusive: true, ..up_to(20) }).is_err());
+        assert!(bounds(&Config { min: 20, max_exclusive: true, ..up_to(20) }).is_err());
+    }
+
+    #[test]
+    fn exclusive_bounds() {
+        let config = Config { min: 10, min_exclusive: true, max_exclusive: true, ..up_to(20) };
+        assert_eq!(bounds(&config).unwrap(), (11, 19));
+
+        let config = Config { min: u64::MAX, min_exclusive: true, ..up_to(u64::MAX) };
+        assert!(bounds(&config).unwrap_err().to_string().contains("`min_exclusive`"));
+
+        let config = Config { max_exclusive: true, ..up_to(0) };
+        assert!(bounds(&config).unwrap_err().to_string().contains("`max_exclusive`"));
+    }
+
+    #[test]
+    fn stream_bounds() {
+        let config = Config { min: 5, max_exclusive: true, ..up_to(100) };
+
+        // Every bound is inherited
+        assert_eq!(config.bounds(&StreamConfig::default()).unwrap(), (5, 99));
+        let stream = StreamConfig { max_exclusive: Some(false), ..Default::default() };
+        assert_eq!(config.bounds(&stream).unwrap(), (5, 100));
+
+        // An upper bound of the stream replaces both top level ones and `max_exclusive`
+        let stream = StreamConfig { max: Some(50), ..Default::default() };
+        assert_eq!(config.bounds(&stream).unwrap(), (5, 50));
+        let stream = StreamConfig { range: Some(50), ..Default::default() };
+        assert_eq!(config.bounds(&stream).unwrap(), (5, 49));
+
+        // The lower bound is inherited separately
+        let stream = StreamConfig { min: Some(7), min_exclusive: Some(true), ..Default::default() };
+        assert_eq!(config.bounds(&stream).unwrap(), (8, 99));
+    }
+
+    #[test]
+    fn settings() {
+        let params = OpenParams::default();
+        assert!(Settings::new(&up_to(10), &params).is_ok());
+        assert!(Settings::new(&Config { batch_size: 0, ..up_to(10) }, &params).is_err());
+        assert!(Settings::new(&Config { batch_time_budget_us: Some(0), ..up_to(10) }, &params).is_err());
+        assert!(Settings::new(&Config { max_lag: MAX_LAG, ..up_to(10) }, &params).is_ok());
+        assert!(Settings::new(&Config { max_lag: usize::MAX, ..up_to(10) }, &params).is_err());
+
+        let stream = |name: &str| StreamConfig { name: String::from(name), ..Default::default() };
+        let streams = vec![stream("a"), stream("b"), stream("a")];
+        let err = Settings::new(&Config { streams, ..up_to(10) }, &params).err().unwrap();
+        assert!(err.to_string().contains("duplicate stream name"));
+
+        let distribution = DistributionConfig::Normal { mean: 5.0, std_dev: 0.0 };
+        assert!(Settings::new(&Config { distribution, ..up_to(10) }, &params).is_err());
+        let rate = RateConfig::Fixed { events_per_second: 0.0 };
+        assert!(Settings::new(&Config { rate, ..up_to(10) }, &params).is_err());
+
+        let config = Config {
+            rate: RateConfig::Unlimited,
+            clock: ClockConfig::Virtual { epoch_ns: None },
+            ..up_to(10)
+        };
+        assert!(Settings::new(&config, &params).is_err());
+    }
+
+    #[test]
+    fn open_params_override() {
+        let params = OpenParams::parse("uniform?min=3&max=4").unwrap();
+        let settings = Settings::new(&up_to(10), &params).unwrap();
+        assert_eq!((settings.streams[0].min, settings.streams[0].max), (3, 4));
+
+        let params = OpenParams::parse("uniform?min=5&max=4").unwrap();
+        assert!(Settings::new(&up_to(10), &params).is_err());
+    }
+}
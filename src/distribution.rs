@@ -0,0 +1,341 @@
+use crate::positive;
+use crate::prob::{beta_i, gamma_p, gamma_q, harmonic, normal_cdf, normal_sf};
+use falco_plugin::anyhow::{anyhow, Error};
+use falco_plugin::schemars::JsonSchema;
+use falco_plugin::serde::{Deserialize, Serialize};
+use rand::Rng;
+use rand_distr::{Binomial, Distribution, Exp, Geometric, LogNormal, Normal, Pareto, Poisson, Zipf};
+
+/// The probability distribution the generated values are drawn from.
+///
+/// Continuous distributions are rounded to the nearest integer, and every
+/// sample is clamped into the configured bounds, so values falling outside
+/// of them pile up on the bounds.
+#[derive(JsonSchema, Deserialize, Serialize, Clone, Debug, Default, PartialEq)]
+#[schemars(crate = "falco_plugin::schemars")]
+#[serde(crate = "falco_plugin::serde", tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum DistributionConfig {
+    /// Every value in the range is equally likely.
+    #[default]
+    Uniform,
+    /// Gaussian distribution.
+    Normal {
+        mean: f64,
+        #[schemars(schema_with = "positive::schema")]
+        std_dev: f64,
+    },
+    /// Exponential distribution with rate `lambda`.
+    Exponential {
+        #[schemars(schema_with = "positive::schema")]
+        lambda: f64,
+    },
+    /// Poisson distribution with mean `lambda`.
+    Poisson {
+        #[schemars(schema_with = "positive::schema")]
+        lambda: f64,
+    },
+    /// Number of successes in `n` trials with success probability `p`.
+    Binomial {
+        n: u64,
+        #[schemars(range(min = 0, max = 1))]
+        p: f64,
+    },
+    /// Number of failures before the first success, with success probability `p`.
+    Geometric {
+        #[schemars(range(min = 0, max = 1))]
+        p: f64,
+    },
+    /// Zipf distribution over `1..=n` with exponent `s`.
+    Zipf {
+        #[schemars(range(min = 1))]
+        n: u64,
+        #[schemars(range(min = 0))]
+        s: f64,
+    },
+    /// Pareto distribution with minimum `scale` and tail index `shape`.
+    Pareto {
+        #[schemars(schema_with = "positive::schema")]
+        scale: f64,
+        #[schemars(schema_with = "positive::schema")]
+        shape: f64,
+    },
+    /// Log-normal distribution whose logarithm has mean `mu` and standard deviation `sigma`.
+    LogNormal {
+        mu: f64,
+        #[schemars(schema_with = "positive::schema")]
+        sigma: f64,
+    },
+}
+
+impl DistributionConfig {
+    /// Probability of drawing `value` within `min..=max`, accounting for the rounding
+    /// of continuous distributions and for the samples piling up on the bounds.
+    pub fn probability(&self, value: u64, min: u64, max: u64) -> f64 {
+        self.range_probability(value, value, min, max)
+    }
+
+    /// Probability of drawing a value in `from..=to` within `min..=max`,
+    /// see [`DistributionConfig::probability`].
+    pub fn range_probability(&self, from: u64, to: u64, min: u64, max: u64) -> f64 {
+        let (from, to) = (from.max(min), to.min(max));
+        if from > to {
+            return 0.0;
+        }
+        if let DistributionConfig::Uniform = self {
+            return ((to - from) as f64 + 1.0) / ((max - min) as f64 + 1.0);
+        }
+
+        // Every sample rounding into the range ends up there, and so does every sample
+        // beyond the bounds it reaches. Integer valued distributions have no mass
+        // between the edges and the values, so the same edges work for them.
+        let lower = if from == min { f64::NEG_INFINITY } else { from as f64 - 0.5 };
+        let upper = if to == max { f64::INFINITY } else { to as f64 + 0.5 };
+        let (cdf_lower, sf_lower) = self.tails(lower);
+        let (cdf_upper, sf_upper) = self.tails(upper);
+        // Subtracting the survival functions keeps the precision in the upper tail
+        let probability = if cdf_lower > 0.5 {
+            sf_lower - sf_upper
+        } else {
+            cdf_upper - cdf_lower
+        };
+        probability.max(0.0)
+    }
+
+    /// Returns the probabilities of a sample being at most `x` and above `x`.
+    fn tails(&self, x: f64) -> (f64, f64) {
+        if x.is_infinite() {
+            return if x > 0.0 { (1.0, 0.0) } else { (0.0, 1.0) };
+        }
+        match *self {
+            // The bounds define the uniform distribution, see `probability`
+            DistributionConfig::Uniform => (0.0, 1.0),
+            DistributionConfig::Normal { mean, std_dev } => {
+                let z = (x - mean) / std_dev;
+                (normal_cdf(z), normal_sf(z))
+            }
+            DistributionConfig::Exponential { lambda } => {
+                if x <= 0.0 {
+                    return (0.0, 1.0);
+                }
+                (-(-lambda * x).exp_m1(), (-lambda * x).exp())
+            }
+            DistributionConfig::Poisson { lambda } => {
+                if x < 0.0 {
+                    return (0.0, 1.0);
+                }
+                let k = x.floor() + 1.0;
+                (gamma_q(k, lambda), gamma_p(k, lambda))
+            }
+            DistributionConfig::Binomial { n, p } => {
+                let (k, n) = (x.floor(), n as f64);
+                if k < 0.0 {
+                    return (0.0, 1.0);
+                }
+                if k >= n {
+                    return (1.0, 0.0);
+                }
+                (beta_i(n - k, k + 1.0, 1.0 - p), beta_i(k + 1.0, n - k, p))
+            }
+            DistributionConfig::Geometric { p } => {
+                if x < 0.0 {
+                    return (0.0, 1.0);
+                }
+                let ln_sf = (x.floor() + 1.0) * (-p).ln_1p();
+                (-ln_sf.exp_m1(), ln_sf.exp())
+            }
+            DistributionConfig::Zipf { n, s } => {
+                let k = x.floor();
+                if k < 1.0 {
+                    return (0.0, 1.0);
+                }
+                if k >= n as f64 {
+                    return (1.0, 0.0);
+                }
+                let total = harmonic(n, s);
+                let below = harmonic(k as u64, s);
+                (below / total, (total - below) / total)
+            }
+            DistributionConfig::Pareto { scale, shape } => {
+                if x <= scale {
+                    return (0.0, 1.0);
+                }
+                let ln_sf = shape * (scale / x).ln();
+                (-ln_sf.exp_m1(), ln_sf.exp())
+            }
+            DistributionConfig::LogNormal { mu, sigma } => {
+                if x <= 0.0 {
+                    return (0.0, 1.0);
+                }
+                let z = (x.ln() - mu) / sigma;
+                (normal_cdf(z), normal_sf(z))
+            }
+        }
+    }
+}
+
+/// A ready to use sampler, built once from a [`DistributionConfig`].
+pub enum Sampler {
+    Uniform,
+    Normal(Normal<f64>),
+    Exponential(Exp<f64>),
+    Poisson(Poisson<f64>),
+    Binomial(Binomial),
+    Geometric(Geometric),
+    Zipf(Zipf<f64>),
+    Pareto(Pareto<f64>),
+    LogNormal(LogNormal<f64>),
+}
+
+impl Sampler {
+    pub fn new(config: &DistributionConfig) -> Result<Self, Error> {
+        // Some distributions accept a zero scale, which the probabilities can't handle
+        match *config {
+            DistributionConfig::Normal { std_dev, .. } => positive::check("std_dev", std_dev)?,
+            DistributionConfig::Exponential { lambda } | DistributionConfig::Poisson { lambda } => {
+                positive::check("lambda", lambda)?
+            }
+            DistributionConfig::Pareto { scale, shape } => {
+                positive::check("scale", scale)?;
+                positive::check("shape", shape)?;
+            }
+            DistributionConfig::LogNormal { sigma, .. } => positive::check("sigma", sigma)?,
+            _ => {}
+        }
+        let sampler = match *config {
+            DistributionConfig::Uniform => Sampler::Uniform,
+            DistributionConfig::Normal { mean, std_dev } => Sampler::Normal(
+                Normal::new(mean, std_dev).map_err(|e| anyhow!("invalid normal distribution: {e}"))?,
+            ),
+            DistributionConfig::Exponential { lambda } => Sampler::Exponential(
+                Exp::new(lambda).map_err(|e| anyhow!("invalid exponential distribution: {e}"))?,
+            ),
+            DistributionConfig::Poisson { lambda } => Sampler::Poisson(
+                Poisson::new(lambda).map_err(|e| anyhow!("invalid poisson distribution: {e}"))?,
+            ),
+            DistributionConfig::Binomial { n, p } => Sampler::Binomial(
+                Binomial::new(n, p).map_err(|e| anyhow!("invalid binomial distribution: {e}"))?,
+            ),
+            DistributionConfig::Geometric { p } => Sampler::Geometric(
+                Geometric::new(p).map_err(|e| anyhow!("invalid geometric distribution: {e}"))?,
+            ),
+            DistributionConfig::Zipf { n, s } => Sampler::Zipf(
+                Zipf::new(n, s).map_err(|e| anyhow!("invalid zipf distribution: {e}"))?,
+            ),
+            DistributionConfig::Pareto { scale, shape } => Sampler::Pareto(
+                Pareto::new(scale, shape).map_err(|e| anyhow!("invalid pareto distribution: {e}"))?,
+            ),
+            DistributionConfig::LogNormal { mu, sigma } => Sampler::LogNormal(
+                LogNormal::new(mu, sigma).map_err(|e| anyhow!("invalid log-normal distribution: {e}"))?,
+            ),
+        };
+        Ok(sampler)
+    }
+
+    /// Draws a single value in `min..=max`, without rounding continuous distributions.
+    pub fn sample_f64<R: Rng + ?Sized>(&self, rng: &mut R, min: u64, max: u64) -> f64 {
+        let (min, max) = (min as f64, max as f64);
+        let value = match self {
+            Sampler::Uniform => return rng.gen_range(min..=max),
+            Sampler::Normal(d) => d.sample(rng),
+            Sampler::Exponential(d) => d.sample(rng),
+            Sampler::Poisson(d) => d.sample(rng),
+            Sampler::Binomial(d) => d.sample(rng) as f64,
+            Sampler::Geometric(d) => d.sample(rng) as f64,
+            Sampler::Zipf(d) => d.sample(rng),
+            Sampler::Pareto(d) => d.sample(rng),
+            Sampler::LogNormal(d) => d.sample(rng),
+        };
+        // `f64::clamp` would keep NaN
+        if value.is_nan() {
+            min
+        } else {
+            value.clamp(min, max)
+        }
+    }
+
+    /// Draws a single value in `min..=max`.
+    pub fn sample<R: Rng + ?Sized>(&self, rng: &mut R, min: u64, max: u64) -> u64 {
+        match self {
+            Sampler::Uniform => rng.gen_range(min..=max),
+            Sampler::Normal(d) => clamp(d.sample(rng), min, max),
+            Sampler::Exponential(d) => clamp(d.sample(rng), min, max),
+            Sampler::Poisson(d) => clamp(d.sample(rng), min, max),
+            Sampler::Binomial(d) => d.sample(rng).clamp(min, max),
+            Sampler::Geometric(d) => d.sample(rng).clamp(min, max),
+            Sampler::Zipf(d) => clamp(d.sample(rng), min, max),
+            Sampler::Pareto(d) => clamp(d.sample(rng), min, max),
+            Sampler::LogNormal(d) => clamp(d.sample(rng), min, max),
+        }
+    }
+}
+
+/// Rounds a continuous sample and maps it onto `min..=max`.
+fn clamp(value: f64, min: u64, max: u64) -> u64 {
+    if value.is_nan() {
+        min
+    } else {
+        // `as` saturates, negative values become 0 and values beyond u64::MAX become u64::MAX
+        (value.round() as u64).clamp(min, max)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn distributions() -> Vec<DistributionConfig> {
+        vec![
+            DistributionConfig::Uniform,
+            DistributionConfig::Normal { mean: 50.0, std_dev: 10.0 },
+            DistributionConfig::Exponential { lambda: 0.1 },
+            DistributionConfig::Poisson { lambda: 30.0 },
+            DistributionConfig::Binomial { n: 80, p: 0.3 },
+            DistributionConfig::Geometric { p: 0.05 },
+            DistributionConfig::Zipf { n: 60, s: 1.2 },
+            DistributionConfig::Pareto { scale: 5.0, shape: 1.5 },
+            DistributionConfig::LogNormal { mu: 3.0, sigma: 0.5 },
+        ]
+    }
+
+    #[test]
+    fn range_probability_sums_to_one() {
+        // Bounds inside the support, around it, and cutting either tail
+        let bounds = [(0, 100), (0, 1000), (20, 40), (45, 46), (7, 7), (1000, u64::MAX)];
+        for (min, max) in bounds {
+            // The bins stop there and a last one covers the rest of the range
+            let cut = max.min(min + 2000);
+            for distribution in distributions() {
+                for width in [1, 3, 16] {
+                    let mut total = distribution.range_probability(cut + 1, max, min, max);
+                    for from in (min..=cut).step_by(width) {
+                        let to = (from + width as u64 - 1).min(cut);
+                        let probability = distribution.range_probability(from, to, min, max);
+                        assert!(probability >= 0.0, "{distribution:?} in {from}..={to}");
+                        total += probability;
+                    }
+                    assert!(
+                        (total - 1.0).abs() < 1e-6,
+                        "{distribution:?} sums to {total} over {min}..={max} in bins of {width}"
+                    );
+                }
+                let whole = distribution.range_probability(min, max, min, max);
+                assert!((whole - 1.0).abs() < 1e-12, "{distribution:?} over {min}..={max}");
+            }
+        }
+    }
+
+    #[test]
+    fn range_probability_values() {
+        let poisson = DistributionConfig::Poisson { lambda: 4.0 };
+        assert!((poisson.probability(2, 0, 100) - 8.0 * (-4f64).exp()).abs() < 1e-12);
+        let binomial = DistributionConfig::Binomial { n: 5, p: 0.5 };
+        assert!((binomial.probability(2, 0, 5) - 10.0 / 32.0).abs() < 1e-12);
+        // Everything from 5 upwards piles up on the upper bound
+        assert!((binomial.probability(4, 0, 4) - 6.0 / 32.0).abs() < 1e-12);
+        let normal = DistributionConfig::Normal { mean: 0.0, std_dev: 1.0 };
+        assert!((normal.range_probability(0, 0, 0, 10) - 0.691_462_461).abs() < 1e-6);
+        // Outside of the bounds
+        assert_eq!(DistributionConfig::Uniform.range_probability(20, 30, 0, 10), 0.0);
+        assert_eq!(DistributionConfig::Uniform.range_probability(5, 30, 0, 10), 6.0 / 11.0);
+    }
+}
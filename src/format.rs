@@ -0,0 +1,216 @@
+use crate::payload::{Payload, Value};
+use falco_plugin::anyhow::{anyhow, bail, Error};
+use serde_json::json;
+use std::fmt::Write;
+
+/// A placeholder of a format template.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Field {
+    Value,
+    Hex,
+    Seq,
+    Stream,
+    Type,
+}
+
+impl Field {
+    fn parse(name: &str) -> Result<Self, Error> {
+        Ok(match name {
+            "value" => Field::Value,
+            "hex" => Field::Hex,
+            "seq" => Field::Seq,
+            "stream" => Field::Stream,
+            "type" => Field::Type,
+            other => bail!("unknown placeholder {{{}}} in format template", other),
+        })
+    }
+}
+
+/// A piece of a format template.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Segment {
+    Literal(String),
+    Field(Field),
+}
+
+/// How events are rendered as text, e.g. by `falco -r` or `sysdig -r`.
+///
+/// `decimal`, `hex` and `json` are built in, anything else is a template where
+/// `{value}`, `{hex}`, `{seq}`, `{stream}` and `{type}` are replaced with the
+/// corresponding event data and `{{`/`}}` stand for literal braces.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Format {
+    Json,
+    Template(Vec<Segment>),
+}
+
+impl Format {
+    pub fn parse(spec: &str) -> Result<Self, Error> {
+        match spec {
+            "decimal" => Self::parse_template("{value}"),
+            "hex" => Self::parse_template("{hex}"),
+            "json" => Ok(Format::Json),
+            template => Self::parse_template(template),
+        }
+    }
+
+    fn parse_template(template: &str) -> Result<Self, Error> {
+        let mut segments = Vec::new();
+        let mut literal = String::new();
+        let mut chars = template.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.as_str().starts_with('{') => {
+                    chars.next();
+                    literal.push('{');
+                }
+                '}' if chars.as_str().starts_with('}') => {
+                    chars.next();
+                    literal.push('}');
+                }
+                '{' => {
+                    let rest = chars.as_str();
+                    let end = rest
+                        .find('}')
+                        .ok_or_else(|| anyhow!("unclosed placeholder in format template {template:?}"))?;
+                    if !literal.is_empty() {
+                        segments.push(Segment::Literal(std::mem::take(&mut literal)));
+                    }
+                    segments.push(Segment::Field(Field::parse(&rest[..end])?));
+                    chars = rest[end + 1..].chars();
+                }
+                '}' => bail!("unmatched '}}' in format template {template:?}"),
+                c => literal.push(c),
+            }
+        }
+        if !literal.is_empty() {
+            segments.push(Segment::Literal(literal));
+        }
+        Ok(Format::Template(segments))
+    }
+
+    /// Renders an event, `stream` being the name of the stream it belongs to.
+    pub fn render(&self, payload: &Payload, stream: &str) -> String {
+        match self {
+            Format::Json => {
+                let value = match &payload.value {
+                    Value::U64(num) => json!(num),
+                    Value::I64(num) => json!(num),
+                    Value::F64(num) => json!(num),
+                    Value::Bool(b) => json!(b),
+                    Value::Str(s) => json!(s),
+                };
+                json!({
+                    "value": value,
+                    "type": payload.value.value_type().name(),
+                    "seq": payload.seq,
+                    "stream": stream,
+                })
+                .to_string()
+            }
+            Format::Template(segments) => {
+                let mut out = String::new();
+                for segment in segments {
+                    // Writing into a String can't fail
+                    let _ = match segment {
+                        Segment::Literal(text) => write!(out, "{text}"),
+                        Segment::Field(Field::Value) => write!(out, "{}", payload.value),
+                        Segment::Field(Field::Hex) => write_hex(&mut out, &payload.value),
+                        Segment::Field(Field::Seq) => match payload.seq {
+                            Some(seq) => write!(out, "{seq}"),
+                            None => write!(out, "-"),
+                        },
+                        Segment::Field(Field::Stream) => write!(out, "{stream}"),
+                        Segment::Field(Field::Type) => write!(out, "{}", payload.value.value_type().name()),
+                    };
+                }
+                out
+            }
+        }
+    }
+}
+
+/// Writes the hexadecimal form of a value: the two's complement for signed integers,
+/// the IEEE 754 bits for floats and the UTF-8 bytes for strings.
+fn write_hex(out: &mut String, value: &Value) -> std::fmt::Result {
+    match value {
+        Value::U64(num) => write!(out, "{num:#x}"),
+        Value::I64(num) => write!(out, "{num:#x}"),
+        Value::F64(num) => write!(out, "{:#x}", num.to_bits()),
+        Value::Bool(b) => write!(out, "{:#x}", u8::from(*b)),
+        Value::Str(s) => {
+            write!(out, "0x")?;
+            s.bytes().try_for_each(|byte| write!(out, "{byte:02x}"))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn placeholders() {
+        let format = Format::parse_template("#{seq} {stream}: {value}").unwrap();
+        assert_eq!(
+            format,
+            Format::Template(vec![
+                Segment::Literal(String::from("#")),
+                Segment::Field(Field::Seq),
+                Segment::Literal(String::from(" ")),
+                Segment::Field(Field::Stream),
+                Segment::Literal(String::from(": ")),
+                Segment::Field(Field::Value),
+            ])
+        );
+        assert_eq!(Format::parse_template("").unwrap(), Format::Template(vec![]));
+    }
+
+    #[test]
+    fn escapes() {
+        let format = Format::parse_template("{{{value}}} }}{{").unwrap();
+        assert_eq!(
+            format,
+            Format::Template(vec![
+                Segment::Literal(String::from("{")),
+                Segment::Field(Field::Value),
+                Segment::Literal(String::from("} }{")),
+            ])
+        );
+    }
+
+    #[test]
+    fn unclosed_placeholder() {
+        let err = Format::parse_template("value: {value").unwrap_err();
+        assert!(err.to_string().contains("unclosed placeholder"));
+        let err = Format::parse_template("{").unwrap_err();
+        assert!(err.to_string().contains("unclosed placeholder"));
+    }
+
+    #[test]
+    fn stray_closing_brace() {
+        let err = Format::parse_template("value} ").unwrap_err();
+        assert!(err.to_string().contains("unmatched '}'"));
+        let err = Format::parse_template("{value}}").unwrap_err();
+        assert!(err.to_string().contains("unmatched '}'"));
+    }
+
+    #[test]
+    fn unknown_placeholder() {
+        let err = Format::parse_template("{val}").unwrap_err();
+        assert!(err.to_string().contains("unknown placeholder {val}"));
+        let err = Format::parse_template("{}").unwrap_err();
+        assert!(err.to_string().contains("unknown placeholder {}"));
+    }
+
+    #[test]
+    fn render() {
+        let payload = Payload::new(0, 4, Value::I64(-1));
+        let format = Format::parse("{type} {value} {hex} #{seq} {{{stream}}}").unwrap();
+        assert_eq!(format.render(&payload, "s"), "i64 -1 0xffffffffffffffff #4 {s}");
+        assert_eq!(Format::parse("decimal").unwrap().render(&payload, "s"), "-1");
+
+        let legacy = Payload::decode(&10u64.to_le_bytes()).unwrap();
+        assert_eq!(Format::parse("{seq} {hex}").unwrap().render(&legacy, "s"), "- 0xa");
+    }
+}
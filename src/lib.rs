@@ -1,43 +1,92 @@
-use falco_plugin::anyhow::{anyhow, Error};
-use falco_plugin::base::{Json, Plugin};
+mod bucket;
+mod clock;
+mod config;
+mod distribution;
+mod format;
+mod number;
+mod params;
+mod payload;
+mod positive;
+mod prob;
+mod rate;
+mod replay;
+mod rng;
+mod sketch;
+mod stats;
+mod value;
+mod window;
+
+pub use crate::config::Config;
+
+use crate::bucket::Buckets;
+use crate::clock::Clock;
+use crate::config::Settings;
+use crate::params::{OpenParams, Source, PRESETS};
+use crate::rate::Rate;
+use crate::payload::{Payload, Value};
+use crate::replay::Replay;
+use crate::rng::GenRng;
+use crate::stats::{ChiSquare, StreamStats};
+use crate::window::SlidingWindow;
+use falco_plugin::anyhow::{anyhow, bail, Context, Error};
+use falco_plugin::base::{Json, Metric, MetricLabel, MetricType, MetricValue, Plugin};
 use falco_plugin::event::events::types::{EventType, PPME_PLUGINEVENT_E};
 use falco_plugin::extract::{field, EventInput, ExtractFieldInfo, ExtractPlugin, ExtractRequest};
-use std::thread;
-use falco_plugin::schemars::JsonSchema;
-use falco_plugin::serde::Deserialize;
+use falco_plugin::parse::{ParseInput, ParsePlugin};
 use falco_plugin::source::{EventBatch, PluginEvent, SourcePlugin, SourcePluginInstance};
-use falco_plugin::{extract_plugin, plugin, source_plugin};
+use falco_plugin::{extract_plugin, parse_plugin, plugin, source_plugin, FailureReason};
 use falco_plugin::strings::CStringWriter;
 use falco_plugin::tables::TablesInput;
-use rand::Rng;
 use std::collections::BTreeMap;
-use std::time::{self, Duration, Instant};
+use std::time::Instant;
 use std::ffi::{CStr, CString};
 use std::io::Write;
-use rand::prelude::ThreadRng;
 
 pub struct RandomGenPlugin {
-    /// Specifies the range within witch the random
-    /// value is generated. The range must be set
-    /// from the plugin configuration.
-    range: u64,
+    /// The plugin configuration, as given by Falco
+    config: Config,
+
+    /// The `open()` parameters of the current capture
+    params: OpenParams,
 
-    /// Keep track of all numbers generated with how
-    /// many times each one occurred
-    histogram: BTreeMap<u64, u64>,
+    /// The generator settings in effect for the current capture
+    settings: Settings,
 
-    /// Random number generator
-    thread_range: ThreadRng,
+    /// Statistics of the parsed events, by stream id
+    stats: BTreeMap<u16, StreamStats>,
 
-    next_event_ts: time::Instant,
+    /// Number of events dropped by the catch-up policy
+    missed_ticks: u64,
+
+    /// Source of the current time, in nanoseconds since the Unix epoch,
+    /// either real or simulated
+    clock: Clock,
+
+    /// Generator state of each stream, in the order of `settings.streams`
+    streams: Vec<StreamState>,
 }
 
-#[derive(JsonSchema, Deserialize)]
-#[schemars(crate = "falco_plugin::schemars")]
-#[serde(crate = "falco_plugin::serde")]
-pub struct Config {
-    /// Defines the random generator range.
-    range: u64,
+/// Generator state of a single stream
+struct StreamState {
+    /// Random number generator, re-seeded each time
+    /// a capture is opened
+    rng: GenRng,
+
+    /// Timestamp of the next event, in nanoseconds since the Unix epoch
+    next_event_ts: u64,
+
+    /// Sequence number of the next event
+    seq: u64,
+}
+
+impl StreamState {
+    fn new(settings: &Settings, index: usize, start_ts: u64) -> Self {
+        Self {
+            rng: settings.stream_rng(index),
+            next_event_ts: start_ts,
+            seq: 0,
+        }
+    }
 }
 
 /// Plugin metadata
@@ -49,21 +98,112 @@ impl Plugin for RandomGenPlugin {
     type ConfigType = Json<Config>;
 
     fn new(_input: Option<&TablesInput>, Json(config): Self::ConfigType) -> Result<Self, Error> {
+        let params = OpenParams::default();
+        let settings = Settings::new(&config, &params).context("invalid plugin configuration")?;
+        let clock = Clock::new(&config.clock);
+        let streams = (0..settings.streams.len())
+            .map(|index| StreamState::new(&settings, index, clock.start()))
+            .collect();
         Ok(Self {
-            range: config.range,
-            histogram: BTreeMap::new(),
-            thread_range: rand::thread_rng(),
-            next_event_ts: time::Instant::now(),
+            config,
+            params,
+            settings,
+            stats: BTreeMap::new(),
+            missed_ticks: 0,
+            clock,
+            streams,
         })
     }
 
-    fn set_config(&mut self, _config: Self::ConfigType) -> Result<(), Error> {
+    /// Applies a new configuration to the running plugin. The open parameters of the
+    /// current capture still take precedence, and the histogram is kept, the bucket
+    /// and chi-square counts being recounted from it when the bins change. Switching
+    /// between real and simulated time would break the event timestamps, so changing
+    /// the clock is rejected, as is adding, removing or renaming streams. Enabling the
+    /// exact histogram would leave out the values parsed so far, so it can't be toggled either.
+    fn set_config(&mut self, Json(config): Self::ConfigType) -> Result<(), Error> {
+        if config.clock != self.config.clock {
+            bail!("the clock cannot be changed while the plugin is running");
+        }
+        if config.exact_histogram != self.config.exact_histogram {
+            bail!("the exact histogram cannot be toggled while the plugin is running");
+        }
+        let settings = Settings::new(&config, &self.params).context("invalid plugin configuration")?;
+        if !settings.same_streams(&self.settings) {
+            bail!("streams cannot be added, removed or renamed while the plugin is running");
+        }
+
+        // Only restart the sequences when they would be different ones anyway
+        if settings.seed != self.settings.seed || settings.algorithm != self.settings.algorithm {
+            for (index, state) in self.streams.iter_mut().enumerate() {
+                state.rng = settings.stream_rng(index);
+            }
+        }
+        // Counts from other buckets would be meaningless
+        if settings.buckets != self.settings.buckets {
+            for stats in self.stats.values_mut() {
+                stats.recount_buckets(settings.buckets.as_ref());
+            }
+        }
+        self.recount_chi_square(&settings);
+        // Don't keep waiting for an event scheduled with the previous rate
+        if let Some(now) = self.clock.now() {
+            for (state, stream) in self.streams.iter_mut().zip(&settings.streams) {
+                state.next_event_ts = state
+                    .next_event_ts
+                    .min(now.saturating_add(stream.rate.mean_interval()));
+            }
+        }
+
+        self.config = config;
+        self.settings = settings;
         Ok(())
     }
+
+    fn get_metrics(&mut self) -> impl IntoIterator<Item = Metric> {
+        [Metric::new(
+            MetricLabel::new(c"missed_ticks", MetricType::Monotonic),
+            MetricValue::U64(self.missed_ticks),
+        )]
+    }
 }
 
 /// Plugin instance
-pub struct RandomGenPluginInstance;
+pub struct RandomGenPluginInstance {
+    /// Number of events generated since the capture was opened
+    emitted: u64,
+
+    /// Timestamp of the first event of the capture
+    start_ts: u64,
+
+    /// File the values are read back from, instead of being generated
+    replay: Option<Replay>,
+}
+
+impl RandomGenPluginInstance {
+    /// Tells whether the event scheduled at `ts` falls beyond the capture limits.
+    fn limit_reached(&self, settings: &Settings, ts: u64) -> bool {
+        settings.max_events.is_some_and(|max| self.emitted >= max)
+            || settings
+                .max_duration
+                .is_some_and(|d| ts >= self.start_ts.saturating_add(d.as_nanos() as u64))
+            || self.replay.as_ref().is_some_and(|replay| replay.current().is_none())
+    }
+
+    /// Picks the stream with the earliest next event, the first one on a tie.
+    /// A replayed file stands for the first stream and the others are idle.
+    fn next_stream(&self, plugin: &RandomGenPlugin) -> usize {
+        let active = match self.replay {
+            Some(_) => 1,
+            None => plugin.streams.len(),
+        };
+        plugin.streams[..active]
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, state)| state.next_event_ts)
+            .map_or(0, |(index, _)| index)
+    }
+}
 
 /// Implement SourcePluginInstance and generate the events
 impl SourcePluginInstance for RandomGenPluginInstance {
@@ -77,21 +217,90 @@ impl SourcePluginInstance for RandomGenPluginInstance {
     /// For performance, events are returned in batches. Of course, it's entirely valid to have
     /// just a single event in a batch.
     ///
+    /// Every event that is due gets added to the batch, up to `batch_size` events or until
+    /// the batch time budget runs out. Each event carries its scheduled timestamp, and the
+    /// events of all streams are interleaved in timestamp order.
+    ///
+    /// When no event is due yet, the call returns a timeout right away instead of blocking
+    /// the event loop, and Falco polls again later. Once `max_events` or `max_duration_secs`
+    /// is reached, or a replayed file has been read to the end, it returns EOF and the capture ends.
+    ///
     fn next_batch(
         &mut self,
         plugin: &mut Self::Plugin,
         batch: &mut EventBatch,
     ) -> Result<(), Error> {
-        if plugin.next_event_ts > time::Instant::now() {
-            thread::sleep(plugin.next_event_ts - time::Instant::now());
+        // Simulated time is never late, so only real time needs catching up. A replay
+        // can't skip records without losing them, so its events all come out late,
+        // and the idle streams have nothing to catch up with.
+        if let (Some(now), None) = (plugin.clock.now(), &self.replay) {
+            for (state, stream) in plugin.streams.iter_mut().zip(&plugin.settings.streams) {
+                plugin.missed_ticks += plugin.settings.catch_up.reschedule(
+                    &mut state.next_event_ts,
+                    now,
+                    stream.rate.mean_interval(),
+                );
+            }
+        }
+        let next_event_ts = plugin.streams[self.next_stream(plugin)].next_event_ts;
+        if self.limit_reached(&plugin.settings, next_event_ts) {
+            return Err(anyhow!("capture limit reached").context(FailureReason::Eof));
+        }
+        if !plugin.clock.is_due(next_event_ts) {
+            return Err(anyhow!("no event due yet").context(FailureReason::Timeout));
         }
-        let num: u64 = plugin.thread_range.gen_range(0..plugin.range);
-        plugin.next_event_ts = plugin.next_event_ts + Duration::from_millis(500);
-        let event = num.to_le_bytes().to_vec();
 
-        // Add the encoded u64 value to the batch
-        let event = Self::plugin_event(&event);
-        batch.add(event)?;
+        let started = Instant::now();
+        for added in 0..plugin.settings.batch_size {
+            let index = self.next_stream(plugin);
+            let state = &mut plugin.streams[index];
+            let stream = &plugin.settings.streams[index];
+            if !plugin.clock.is_due(state.next_event_ts) {
+                break;
+            }
+            // Always make progress, however small the budget
+            if added > 0
+                && plugin.settings.batch_time_budget.is_some_and(|budget| started.elapsed() >= budget)
+            {
+                break;
+            }
+            if self.limit_reached(&plugin.settings, state.next_event_ts) {
+                break;
+            }
+
+            let ts = state.next_event_ts;
+            let (value, interval) = match self.replay.as_mut() {
+                Some(replay) => {
+                    let num = replay
+                        .current()
+                        .ok_or_else(|| anyhow!("replay file exhausted"))?;
+                    // Records without timestamps are spaced according to the configured rate
+                    let interval = match replay.advance()? {
+                        Some(delay) => delay,
+                        None => stream.rate.next_interval(&mut state.rng),
+                    };
+                    (Value::U64(num), interval)
+                }
+                None => {
+                    let value = stream.draw(&mut state.rng);
+                    (value, stream.rate.next_interval(&mut state.rng))
+                }
+            };
+            state.next_event_ts = ts.saturating_add(interval);
+            // An unlimited stream is always due, its events are stamped with the current time
+            if let (Rate::Unlimited, Some(now)) = (&stream.rate, plugin.clock.now()) {
+                state.next_event_ts = state.next_event_ts.max(now);
+            }
+            // Stream ids fit in 16 bits, the configuration can't list more streams
+            let event = Payload::new(index as u16, state.seq, value).encode();
+            state.seq += 1;
+
+            // Add the encoded payload to the batch
+            let mut event = Self::plugin_event(&event);
+            event.metadata.ts = ts;
+            batch.add(event)?;
+            self.emitted += 1;
+        }
 
         Ok(())
     }
@@ -103,10 +312,37 @@ impl SourcePlugin for RandomGenPlugin {
     const EVENT_SOURCE: &'static CStr = c"random_generator";
     const PLUGIN_ID: u32 = 1423;
 
-    fn open(&mut self, _params: Option<&str>) -> Result<Self::Instance, Error> {
-        Ok(RandomGenPluginInstance)
+    fn list_open_params(&mut self) -> Result<&CStr, Error> {
+        Ok(PRESETS)
     }
 
+    /// The `params` override the plugin configuration for this capture only,
+    /// see [`OpenParams`] for their format.
+    fn open(&mut self, params: Option<&str>) -> Result<Self::Instance, Error> {
+        let params = OpenParams::parse(params.unwrap_or_default())?;
+        let settings = Settings::new(&self.config, &params)?;
+        let replay = match &params.source {
+            Source::Replay { path, speed } => Some(Replay::open(path, *speed)?),
+            Source::Configured | Source::Distribution(_) => None,
+        };
+        // A failed open leaves the settings of the previous capture in place
+        self.recount_chi_square(&settings);
+        self.settings = settings;
+        self.params = params;
+
+        // Start every capture from the beginning of the sequences
+        let start_ts = self.clock.start();
+        self.streams = (0..self.settings.streams.len())
+            .map(|index| StreamState::new(&self.settings, index, start_ts))
+            .collect();
+        Ok(RandomGenPluginInstance {
+            emitted: 0,
+            start_ts,
+            replay,
+        })
+    }
+
+    /// Renders the event according to the configured `format`.
     fn event_to_string(&mut self, event: &EventInput) -> Result<CString, Error> {
         // Make sure we have a plugin event and parse it into individual fields
         let event = event.event()?;
@@ -115,11 +351,14 @@ impl SourcePlugin for RandomGenPlugin {
         // All event fields are optional, so we have to check if the data is actually there
         match event.params.event_data {
             Some(payload) => {
+                let payload = Payload::decode(payload)?;
+
                 // CStringWriter is a small helper that lets you write arbitrary data
                 // (e.g. using format strings) into CStrings. Note that as CStrings cannot
                 // contain NUL bytes, any attempt to write one will fail.
                 let mut writer = CStringWriter::default();
-                writer.write_all(payload)?;
+                let stream = self.stream_name(payload.stream);
+                writer.write_all(self.settings.format.render(&payload, &stream).as_bytes())?;
                 Ok(writer.into_cstring())
             }
             None => Ok(CString::new("<no payload>")?),
@@ -128,30 +367,401 @@ impl SourcePlugin for RandomGenPlugin {
 }
 
 impl RandomGenPlugin {
-    pub fn new(range: u64, histogram: BTreeMap<u64, u64>, thread_range: ThreadRng, next_event_ts: Instant) -> Self {
-        Self { range, histogram, thread_range, next_event_ts }
+    /// Name of a stream of the current configuration, or its id when there's no such stream,
+    /// e.g. for an event read back from a capture file.
+    fn stream_name(&self, stream: u16) -> String {
+        match self.settings.streams.get(usize::from(stream)) {
+            Some(settings) => settings.name.clone(),
+            None => stream.to_string(),
+        }
     }
 
-    /// Reads the raw event payload and converts it to u64 value.
-    fn extract_number(&mut self, req: ExtractRequest<Self>) -> Result<u64, Error> {
-        let event = req.event.event()?;
+    /// Counts the histogram again into the chi-square bins of the streams whose bounds or
+    /// distribution differ in `settings`, before they replace the current ones.
+    fn recount_chi_square(&mut self, settings: &Settings) {
+        for (&stream, stats) in &mut self.stats {
+            let new = settings.streams.get(usize::from(stream));
+            let old = self.settings.streams.get(usize::from(stream));
+            if new.map(|new| &new.chi_square_bins) != old.map(|old| &old.chi_square_bins) {
+                stats.recount_chi_square(new);
+            }
+        }
+    }
+
+    /// Reads the raw event payload and decodes it, whatever its format version.
+    fn decode_payload(event: &EventInput) -> Result<Payload, Error> {
+        let event = event.event()?;
         let event = event.load::<PluginEvent>()?;
         let buf = event
             .params
             .event_data
             .ok_or_else(|| anyhow!("Missing event data"))?;
-        Ok(u64::from_le_bytes(buf.try_into()?))
+        Payload::decode(buf)
+    }
+
+    /// Reads the raw event payload and converts it to u64 value.
+    fn decode_number(event: &EventInput) -> Result<u64, Error> {
+        match Self::decode_payload(event)?.value {
+            Value::U64(num) => Ok(num),
+            other => Err(anyhow!("not a u64 value: {}", other.value_type().name())),
+        }
+    }
+
+    fn extract_number(&mut self, req: ExtractRequest<Self>) -> Result<u64, Error> {
+        Self::decode_number(req.event)
+    }
+
+    // Properties of the u64 value, for simple conditions on the number itself
+
+    fn extract_hex(&mut self, req: ExtractRequest<Self>) -> Result<CString, Error> {
+        let num = Self::decode_number(req.event)?;
+        Ok(CString::new(format!("{num:#x}"))?)
+    }
+
+    fn extract_bin(&mut self, req: ExtractRequest<Self>) -> Result<CString, Error> {
+        let num = Self::decode_number(req.event)?;
+        Ok(CString::new(format!("{num:#b}"))?)
+    }
+
+    fn extract_popcount(&mut self, req: ExtractRequest<Self>) -> Result<u64, Error> {
+        Ok(u64::from(Self::decode_number(req.event)?.count_ones()))
+    }
+
+    fn extract_leading_zeros(&mut self, req: ExtractRequest<Self>) -> Result<u64, Error> {
+        Ok(u64::from(Self::decode_number(req.event)?.leading_zeros()))
+    }
+
+    fn extract_is_even(&mut self, req: ExtractRequest<Self>) -> Result<bool, Error> {
+        Ok(Self::decode_number(req.event)?.is_multiple_of(2))
+    }
+
+    fn extract_is_prime(&mut self, req: ExtractRequest<Self>) -> Result<bool, Error> {
+        Ok(number::is_prime(Self::decode_number(req.event)?))
+    }
+
+    fn extract_mod(&mut self, req: ExtractRequest<Self>, n: u64) -> Result<u64, Error> {
+        let num = Self::decode_number(req.event)?;
+        num.checked_rem(n)
+            .ok_or_else(|| anyhow!("modulo by zero"))
+    }
+
+    fn extract_digits(&mut self, req: ExtractRequest<Self>) -> Result<u64, Error> {
+        Ok(number::digits(Self::decode_number(req.event)?))
+    }
+
+    // The Falco plugin API has no signed integer nor floating point field types,
+    // so `gen.int` and `gen.float` are rendered as strings.
+
+    fn extract_int(&mut self, req: ExtractRequest<Self>) -> Result<CString, Error> {
+        match Self::decode_payload(req.event)?.value {
+            Value::I64(num) => Ok(CString::new(num.to_string())?),
+            other => Err(anyhow!("not an i64 value: {}", other.value_type().name())),
+        }
+    }
+
+    fn extract_float(&mut self, req: ExtractRequest<Self>) -> Result<CString, Error> {
+        match Self::decode_payload(req.event)?.value {
+            Value::F64(num) => Ok(CString::new(num.to_string())?),
+            other => Err(anyhow!("not an f64 value: {}", other.value_type().name())),
+        }
     }
 
-    fn extract_count(&mut self, _req: ExtractRequest<Self>, num: u64) -> Result<u64, Error> {
-        // Get the count of occurrences of `num` from `self.histogram`.
+    fn extract_bool(&mut self, req: ExtractRequest<Self>) -> Result<bool, Error> {
+        match Self::decode_payload(req.event)?.value {
+            Value::Bool(b) => Ok(b),
+            other => Err(anyhow!("not a bool value: {}", other.value_type().name())),
+        }
+    }
+
+    fn extract_str(&mut self, req: ExtractRequest<Self>) -> Result<CString, Error> {
+        match Self::decode_payload(req.event)?.value {
+            Value::Str(s) => Ok(CString::new(s)?),
+            other => Err(anyhow!("not a string value: {}", other.value_type().name())),
+        }
+    }
+
+    fn extract_stream(&mut self, req: ExtractRequest<Self>) -> Result<CString, Error> {
+        let stream = Self::decode_payload(req.event)?.stream;
+        Ok(CString::new(self.stream_name(stream))?)
+    }
+
+    fn extract_seq(&mut self, req: ExtractRequest<Self>) -> Result<u64, Error> {
+        Self::decode_payload(req.event)?
+            .seq
+            .ok_or_else(|| anyhow!("no sequence number in legacy payload"))
+    }
+
+    fn extract_seq_gap(&mut self, req: ExtractRequest<Self>) -> Result<u64, Error> {
+        // The gap was measured when the event went through `parse_event`
+        let payload = Self::decode_payload(req.event)?;
+        let seq = payload
+            .seq
+            .ok_or_else(|| anyhow!("no sequence number in legacy payload"))?;
+        match self.stats.get(&payload.stream) {
+            Some(stats) if stats.last_seq == Some(seq) => Ok(stats.seq_gap),
+            _ => Err(anyhow!("event {seq} was not parsed")),
+        }
+    }
+
+    /// Returns the sliding window of the event's stream.
+    fn window(&self, event: &EventInput) -> Result<&SlidingWindow, Error> {
+        let stream = Self::decode_payload(event)?.stream;
+        self.stats
+            .get(&stream)
+            .map(|stats| &stats.window)
+            .ok_or_else(|| anyhow!("no event parsed in stream {stream}"))
+    }
+
+    // The window statistics are rounded to the nearest integer, like the values they
+    // are compared with. They have no value while the window is empty.
+
+    fn extract_mean(&mut self, req: ExtractRequest<Self>) -> Result<u64, Error> {
+        let mean = self.window(req.event)?.mean();
+        mean.map(|mean| mean.round() as u64)
+            .ok_or_else(|| anyhow!("empty window"))
+    }
+
+    fn extract_stddev(&mut self, req: ExtractRequest<Self>) -> Result<u64, Error> {
+        let stddev = self.window(req.event)?.stddev();
+        stddev
+            .map(|stddev| stddev.round() as u64)
+            .ok_or_else(|| anyhow!("empty window"))
+    }
+
+    fn extract_min(&mut self, req: ExtractRequest<Self>) -> Result<u64, Error> {
+        self.window(req.event)?
+            .min()
+            .ok_or_else(|| anyhow!("empty window"))
+    }
+
+    fn extract_max(&mut self, req: ExtractRequest<Self>) -> Result<u64, Error> {
+        self.window(req.event)?
+            .max()
+            .ok_or_else(|| anyhow!("empty window"))
+    }
+
+    fn extract_median(&mut self, req: ExtractRequest<Self>) -> Result<u64, Error> {
+        let median = self.window(req.event)?.median();
+        median
+            .map(|median| median.round() as u64)
+            .ok_or_else(|| anyhow!("empty window"))
+    }
+
+    /// Estimates the `p`th percentile of all the values of the event's stream.
+    fn extract_percentile(&mut self, req: ExtractRequest<Self>, p: u64) -> Result<u64, Error> {
+        if p > 100 {
+            bail!("percentile {p} out of range 0-100");
+        }
+        let stream = Self::decode_payload(req.event)?.stream;
+        self.stats
+            .get(&stream)
+            .and_then(|stats| stats.sketch.quantile(p as f64 / 100.0))
+            .ok_or_else(|| anyhow!("no value parsed in stream {stream}"))
+    }
+
+    /// Returns the value `n` events back in the event's stream, 0 being the event itself.
+    fn lag(&self, event: &EventInput, n: u64) -> Result<u64, Error> {
+        let stream = Self::decode_payload(event)?.stream;
+        usize::try_from(n)
+            .ok()
+            .and_then(|n| self.stats.get(&stream)?.lag(n))
+            .ok_or_else(|| anyhow!("no value {n} events back in stream {stream}"))
+    }
+
+    fn extract_prev(&mut self, req: ExtractRequest<Self>) -> Result<u64, Error> {
+        self.lag(req.event, 1)
+    }
+
+    // The difference may be negative, so like `gen.int` it's rendered as a string,
+    // and `gen.abs_delta` gives its magnitude for numeric comparisons.
+
+    fn extract_delta(&mut self, req: ExtractRequest<Self>) -> Result<CString, Error> {
+        let delta = i128::from(self.lag(req.event, 0)?) - i128::from(self.lag(req.event, 1)?);
+        Ok(CString::new(delta.to_string())?)
+    }
+
+    fn extract_abs_delta(&mut self, req: ExtractRequest<Self>) -> Result<u64, Error> {
+        Ok(self.lag(req.event, 0)?.abs_diff(self.lag(req.event, 1)?))
+    }
+
+    fn extract_lag(&mut self, req: ExtractRequest<Self>, n: u64) -> Result<u64, Error> {
+        self.lag(req.event, n)
+    }
+
+    // Anomaly scores are rounded down to an integer, so `gen.abs_zscore >= 3` matches the
+    // values at least 3 standard deviations away from the mean. Like `gen.delta`, the
+    // signed z-score is rendered as a string.
+
+    /// Distance between the value and the mean of the previous values of the event's
+    /// stream, in standard deviations, negative below the mean.
+    fn zscore(&self, event: &EventInput) -> Result<f64, Error> {
+        let stream = Self::decode_payload(event)?.stream;
+        self.stats
+            .get(&stream)
+            .and_then(|stats| stats.zscore)
+            .ok_or_else(|| anyhow!("no z-score in stream {stream}"))
+    }
+
+    fn extract_zscore(&mut self, req: ExtractRequest<Self>) -> Result<CString, Error> {
+        // Rounded towards zero, so that it's the opposite of `gen.abs_zscore` below the mean
+        let zscore = self.zscore(req.event)?.trunc() as i64;
+        Ok(CString::new(zscore.to_string())?)
+    }
+
+    fn extract_abs_zscore(&mut self, req: ExtractRequest<Self>) -> Result<u64, Error> {
+        Ok(self.zscore(req.event)?.abs().floor() as u64)
+    }
+
+    /// Information content of the value, in bits, under the distribution of its stream.
+    fn extract_surprisal(&mut self, req: ExtractRequest<Self>) -> Result<u64, Error> {
+        let payload = Self::decode_payload(req.event)?;
+        let Value::U64(num) = payload.value else {
+            bail!("not a u64 value: {}", payload.value.value_type().name());
+        };
+        let replay = matches!(self.params.source, Source::Replay { .. });
+        let probability = match self.settings.streams.get(usize::from(payload.stream)) {
+            Some(stream) if !replay => stream.distribution.probability(num, stream.min, stream.max),
+            // Values not drawn from a known distribution are scored against their
+            // observed frequency instead
+            _ => {
+                self.check_histogram()?;
+                self.stats
+                    .get(&payload.stream)
+                    .map_or(0.0, |stats| stats.frequency(num))
+            }
+        };
+        // `as` saturates, so values that can't be drawn at all get u64::MAX
+        Ok((-probability.log2()).floor() as u64)
+    }
+
+    // Goodness of fit tests, rounded down to an integer for the statistic, in parts
+    // per million for the p-values and in millibits for the entropy.
+
+    fn chi_square(&self, event: &EventInput) -> Result<ChiSquare, Error> {
+        self.check_histogram()?;
+        let stream = Self::decode_payload(event)?.stream;
+        let settings = self
+            .settings
+            .streams
+            .get(usize::from(stream))
+            .ok_or_else(|| anyhow!("unknown stream {stream}"))?;
+        self.stats
+            .get(&stream)
+            .and_then(|stats| stats.chi_square(settings))
+            .ok_or_else(|| anyhow!("not enough values in stream {stream}"))
+    }
+
+    fn extract_chi2(&mut self, req: ExtractRequest<Self>) -> Result<u64, Error> {
+        Ok(self.chi_square(req.event)?.statistic.floor() as u64)
+    }
+
+    fn extract_chi2_pvalue(&mut self, req: ExtractRequest<Self>) -> Result<u64, Error> {
+        Ok(prob::ppm(self.chi_square(req.event)?.pvalue))
+    }
+
+    fn extract_runs_pvalue(&mut self, req: ExtractRequest<Self>) -> Result<u64, Error> {
+        let pvalue = self.window(req.event)?.runs_pvalue();
+        pvalue
+            .map(prob::ppm)
+            .ok_or_else(|| anyhow!("not enough distinct values in window"))
+    }
+
+    fn extract_entropy(&mut self, req: ExtractRequest<Self>) -> Result<u64, Error> {
+        let entropy = self.window(req.event)?.entropy();
+        entropy
+            .map(|entropy| (entropy * 1000.0).round() as u64)
+            .ok_or_else(|| anyhow!("empty window"))
+    }
+
+    /// Fails unless the exact histogram is kept, for the fields it backs.
+    fn check_histogram(&self) -> Result<(), Error> {
+        if !self.settings.exact_histogram {
+            bail!("exact histogram disabled");
+        }
+        Ok(())
+    }
+
+    fn extract_count(&mut self, req: ExtractRequest<Self>, num: u64) -> Result<u64, Error> {
+        // Get the count of occurrences of `num` from the histogram of the event's stream.
         // If the number isn't there (hasn't been generated even once),
         // return zero
-        match self.histogram.get(&num) {
+        self.check_histogram()?;
+        let stream = Self::decode_payload(req.event)?.stream;
+        match self.stats.get(&stream).and_then(|stats| stats.histogram.get(&num)) {
             Some(count) => Ok(*count),
             None => Ok(0),
         }
     }
+
+    /// Counts the values of the event's stream between the bounds of `range`, given as
+    /// `a:b`. Both bounds are included and either of them may be omitted.
+    fn extract_count_range(&mut self, req: ExtractRequest<Self>, range: &CStr) -> Result<u64, Error> {
+        let range = range.to_str()?;
+        let (from, to) = range
+            .split_once(':')
+            .ok_or_else(|| anyhow!("invalid range {range:?}, expected a:b"))?;
+        let parse = |bound: &str| {
+            (!bound.is_empty())
+                .then(|| bound.parse::<u64>())
+                .transpose()
+                .map_err(|e| anyhow!("invalid bound {bound:?} in range {range:?}: {e}"))
+        };
+        let from = parse(from)?.unwrap_or(u64::MIN);
+        let to = parse(to)?.unwrap_or(u64::MAX);
+        if from > to {
+            bail!("empty range {range:?}");
+        }
+        self.check_histogram()?;
+
+        let stream = Self::decode_payload(req.event)?.stream;
+        Ok(self
+            .stats
+            .get(&stream)
+            .map_or(0, |stats| stats.count_range(from..=to)))
+    }
+
+    /// Returns the histogram buckets and the index of the one the event's value falls in.
+    fn bucket(&self, event: &EventInput) -> Result<(&Buckets, usize), Error> {
+        let num = Self::decode_number(event)?;
+        let buckets = self
+            .settings
+            .buckets
+            .as_ref()
+            .ok_or_else(|| anyhow!("no histogram buckets configured"))?;
+        Ok((buckets, buckets.index(num)))
+    }
+
+    /// Lower bound of the bucket the value falls in.
+    fn extract_bucket(&mut self, req: ExtractRequest<Self>) -> Result<u64, Error> {
+        let (buckets, index) = self.bucket(req.event)?;
+        Ok(buckets.lower_bound(index))
+    }
+
+    /// Number of values of the event's stream in the bucket the value falls in.
+    fn extract_bucket_count(&mut self, req: ExtractRequest<Self>) -> Result<u64, Error> {
+        let (_, index) = self.bucket(req.event)?;
+        let stream = Self::decode_payload(req.event)?.stream;
+        Ok(self
+            .stats
+            .get(&stream)
+            .and_then(|stats| stats.bucket_counts.get(index))
+            .copied()
+            .unwrap_or(0))
+    }
+
+    fn extract_distinct(&mut self, req: ExtractRequest<Self>) -> Result<u64, Error> {
+        self.check_histogram()?;
+        let stream = Self::decode_payload(req.event)?.stream;
+        Ok(self
+            .stats
+            .get(&stream)
+            .map_or(0, |stats| stats.histogram.len() as u64))
+    }
+
+    fn extract_total(&mut self, req: ExtractRequest<Self>) -> Result<u64, Error> {
+        let stream = Self::decode_payload(req.event)?.stream;
+        Ok(self.stats.get(&stream).map_or(0, |stats| stats.total))
+    }
 }
 
 /// Implement the field extraction capability
@@ -177,9 +787,71 @@ impl ExtractPlugin for RandomGenPlugin {
     const EXTRACT_FIELDS: &'static [ExtractFieldInfo<Self>] = &[
         field("gen.num", &Self::extract_number),
         field("gen.count", &Self::extract_count),
+        field("gen.count_range", &Self::extract_count_range),
+        field("gen.bucket", &Self::extract_bucket),
+        field("gen.bucket_count", &Self::extract_bucket_count),
+        field("gen.distinct", &Self::extract_distinct),
+        field("gen.total", &Self::extract_total),
+        field("gen.hex", &Self::extract_hex),
+        field("gen.bin", &Self::extract_bin),
+        field("gen.popcount", &Self::extract_popcount),
+        field("gen.leading_zeros", &Self::extract_leading_zeros),
+        field("gen.is_even", &Self::extract_is_even),
+        field("gen.is_prime", &Self::extract_is_prime),
+        field("gen.mod", &Self::extract_mod),
+        field("gen.digits", &Self::extract_digits),
+        field("gen.int", &Self::extract_int),
+        field("gen.float", &Self::extract_float),
+        field("gen.bool", &Self::extract_bool),
+        field("gen.str", &Self::extract_str),
+        field("gen.stream", &Self::extract_stream),
+        field("gen.seq", &Self::extract_seq),
+        field("gen.seq_gap", &Self::extract_seq_gap),
+        field("gen.mean", &Self::extract_mean),
+        field("gen.stddev", &Self::extract_stddev),
+        field("gen.min", &Self::extract_min),
+        field("gen.max", &Self::extract_max),
+        field("gen.median", &Self::extract_median),
+        field("gen.percentile", &Self::extract_percentile),
+        field("gen.zscore", &Self::extract_zscore),
+        field("gen.abs_zscore", &Self::extract_abs_zscore),
+        field("gen.surprisal", &Self::extract_surprisal),
+        field("gen.chi2", &Self::extract_chi2),
+        field("gen.chi2_pvalue", &Self::extract_chi2_pvalue),
+        field("gen.runs_pvalue", &Self::extract_runs_pvalue),
+        field("gen.entropy", &Self::extract_entropy),
+        field("gen.prev", &Self::extract_prev),
+        field("gen.delta", &Self::extract_delta),
+        field("gen.abs_delta", &Self::extract_abs_delta),
+        field("gen.lag", &Self::extract_lag),
     ];
 }
 
+/// Implement the event parsing capability
+/// https://falco.org/docs/plugins/architecture/#event-parsing-capability
+///
+/// Every event, whether it was just produced by `next_batch` or read back from a
+/// capture file, goes through `parse_event` exactly once before fields are extracted
+/// from it. This is where the histogram gets updated, so that `gen.count` stays
+/// correct in both cases without counting live events twice. Each stream has its
+/// own histogram, and only u64 values are counted. The sequence gaps, the sliding
+/// windows and the recent values are maintained here too, for the same reason.
+impl ParsePlugin for RandomGenPlugin {
+    const EVENT_TYPES: &'static [EventType] = &[];
+    const EVENT_SOURCES: &'static [&'static str] = &["random_generator"];
+
+    fn parse_event(&mut self, event: &EventInput, _parse_input: &ParseInput) -> Result<(), Error> {
+        let payload = Self::decode_payload(event)?;
+        let ts = event.event()?.metadata.ts;
+        self.stats
+            .entry(payload.stream)
+            .or_default()
+            .record(&payload, ts, &self.settings);
+        Ok(())
+    }
+}
+
 plugin!(RandomGenPlugin);
 source_plugin!(RandomGenPlugin);
-extract_plugin!(RandomGenPlugin);
\ No newline at end of file
+extract_plugin!(RandomGenPlugin);
+parse_plugin!(RandomGenPlugin);
\ No newline at end of file
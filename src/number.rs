@@ -0,0 +1,86 @@
+/// Tells whether `n` is a prime number.
+///
+/// Miller-Rabin test with the first 12 primes as witnesses,
+/// which is deterministic for every 64 bit number.
+pub fn is_prime(n: u64) -> bool {
+    const WITNESSES: [u64; 12] = [2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37];
+
+    if n < 2 {
+        return false;
+    }
+    if let Some(&p) = WITNESSES.iter().find(|&&p| n.is_multiple_of(p)) {
+        return n == p;
+    }
+
+    // n - 1 = d * 2^s, d odd
+    let s = (n - 1).trailing_zeros();
+    let d = (n - 1) >> s;
+    WITNESSES.iter().all(|&a| {
+        let mut x = pow_mod(a, d, n);
+        if x == 1 || x == n - 1 {
+            return true;
+        }
+        for _ in 1..s {
+            x = mul_mod(x, x, n);
+            if x == n - 1 {
+                return true;
+            }
+        }
+        false
+    })
+}
+
+fn mul_mod(a: u64, b: u64, m: u64) -> u64 {
+    (u128::from(a) * u128::from(b) % u128::from(m)) as u64
+}
+
+fn pow_mod(mut base: u64, mut exp: u64, m: u64) -> u64 {
+    let mut result = 1;
+    base %= m;
+    while exp > 0 {
+        if exp & 1 == 1 {
+            result = mul_mod(result, base, m);
+        }
+        base = mul_mod(base, base, m);
+        exp >>= 1;
+    }
+    result
+}
+
+/// Number of decimal digits of `n`.
+pub fn digits(n: u64) -> u64 {
+    n.checked_ilog10().map_or(1, |log| u64::from(log) + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn primes() {
+        for n in [2, 3, 37, 41, (1 << 61) - 1, u64::MAX - 58] {
+            assert!(is_prime(n), "{n} is prime");
+        }
+        // 561 is a Carmichael number and 3215031751 a strong pseudoprime
+        // to the bases 2, 3, 5 and 7
+        for n in [0, 1, 4, 561, 3215031751, u64::MAX] {
+            assert!(!is_prime(n), "{n} is not prime");
+        }
+    }
+
+    #[test]
+    fn primes_below_10000() {
+        for n in 0..10_000u64 {
+            let prime = n >= 2 && (2..n).take_while(|d| d * d <= n).all(|d| !n.is_multiple_of(d));
+            assert_eq!(is_prime(n), prime, "{n}");
+        }
+    }
+
+    #[test]
+    fn digit_counts() {
+        assert_eq!(digits(0), 1);
+        assert_eq!(digits(9), 1);
+        assert_eq!(digits(10), 2);
+        assert_eq!(digits(u64::MAX), 20);
+    }
+}
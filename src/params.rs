@@ -0,0 +1,233 @@
+use crate::distribution::DistributionConfig;
+use falco_plugin::anyhow::{anyhow, bail, Error};
+use std::collections::BTreeMap;
+use std::ffi::CStr;
+use std::fmt::Display;
+use std::str::FromStr;
+
+/// Sample `open()` parameters, advertised to Falco through `list_open_params`.
+pub const PRESETS: &CStr = c"[\
+{\"value\":\"uniform?min=0&max=1000\",\"desc\":\"Uniformly distributed values between 0 and 1000\",\"separator\":\"\"},\
+{\"value\":\"normal?mean=500&sd=50\",\"desc\":\"Normally distributed values around 500\",\"separator\":\"\"},\
+{\"value\":\"exponential?lambda=0.01\",\"desc\":\"Exponentially distributed values averaging 100\",\"separator\":\"\"},\
+{\"value\":\"poisson?lambda=10\",\"desc\":\"Poisson distributed values averaging 10\",\"separator\":\"\"},\
+{\"value\":\"zipf?n=1000&s=1.1\",\"desc\":\"Zipf distributed values between 1 and 1000\",\"separator\":\"\"},\
+{\"value\":\"pareto?scale=10&shape=1.5\",\"desc\":\"Pareto distributed values above 10\",\"separator\":\"\"},\
+{\"value\":\"replay:///path/to/file.jsonl?speed=1\",\"desc\":\"Values read back from a JSON lines file\",\"separator\":\"\"}\
+]";
+
+/// Where the values of a capture come from.
+#[derive(Debug, Default)]
+pub enum Source {
+    /// Values are drawn from the distribution in the plugin configuration.
+    #[default]
+    Configured,
+    /// Values are drawn from the given distribution.
+    Distribution(DistributionConfig),
+    /// Values are read back from a JSON lines file, with the gaps between
+    /// recorded timestamps divided by `speed`.
+    Replay { path: String, speed: f64 },
+}
+
+/// Per-capture overrides of the plugin configuration, parsed from the `open()` parameters.
+///
+/// The parameters look like an URI: a distribution name (or `replay://` followed by a file
+/// path) and an optional query string, e.g. `normal?mean=5&sd=2&seed=42`. Besides the
+/// distribution parameters, the query string accepts `min`, `max`, `seed`, `rate`
+/// (events per second), `max_events` and `max_duration_secs`.
+#[derive(Debug, Default)]
+pub struct OpenParams {
+    pub source: Source,
+    pub min: Option<u64>,
+    pub max: Option<u64>,
+    pub seed: Option<u64>,
+    pub events_per_second: Option<f64>,
+    pub max_events: Option<u64>,
+    pub max_duration_secs: Option<u64>,
+}
+
+impl OpenParams {
+    pub fn parse(spec: &str) -> Result<Self, Error> {
+        let spec = spec.trim();
+        let (name, query) = spec.split_once('?').unwrap_or((spec, ""));
+        let mut args = Args::parse(query)?;
+
+        let source = match name.strip_prefix("replay://") {
+            Some(path) => Source::Replay {
+                path: path.to_string(),
+                speed: args.take("speed")?.unwrap_or(1.0),
+            },
+            None => match name {
+                "" => Source::Configured,
+                "uniform" => Source::Distribution(DistributionConfig::Uniform),
+                "normal" => Source::Distribution(DistributionConfig::Normal {
+                    mean: args.require("mean")?,
+                    std_dev: match args.take("sd")? {
+                        Some(sd) => sd,
+                        None => args.require("std_dev")?,
+                    },
+                }),
+                "exponential" => Source::Distribution(DistributionConfig::Exponential {
+                    lambda: args.require("lambda")?,
+                }),
+                "poisson" => Source::Distribution(DistributionConfig::Poisson {
+                    lambda: args.require("lambda")?,
+                }),
+                "binomial" => Source::Distribution(DistributionConfig::Binomial {
+                    n: args.require("n")?,
+                    p: args.require("p")?,
+                }),
+                "geometric" => Source::Distribution(DistributionConfig::Geometric {
+                    p: args.require("p")?,
+                }),
+                "zipf" => Source::Distribution(DistributionConfig::Zipf {
+                    n: args.require("n")?,
+                    s: args.require("s")?,
+                }),
+                "pareto" => Source::Distribution(DistributionConfig::Pareto {
+                    scale: args.require("scale")?,
+                    shape: args.require("shape")?,
+                }),
+                "log_normal" => Source::Distribution(DistributionConfig::LogNormal {
+                    mu: args.require("mu")?,
+                    sigma: args.require("sigma")?,
+                }),
+                other => bail!("unknown generator {other:?}"),
+            },
+        };
+
+        let params = OpenParams {
+            source,
+            min: args.take("min")?,
+            max: args.take("max")?,
+            seed: args.take("seed")?,
+            events_per_second: args.take("rate")?,
+            max_events: args.take("max_events")?,
+            max_duration_secs: args.take("max_duration_secs")?,
+        };
+        args.finish()?;
+        Ok(params)
+    }
+}
+
+/// The `key=value` pairs of a query string.
+struct Args<'a>(BTreeMap<&'a str, &'a str>);
+
+impl<'a> Args<'a> {
+    fn parse(query: &'a str) -> Result<Self, Error> {
+        let mut args = BTreeMap::new();
+        for pair in query.split('&').filter(|pair| !pair.is_empty()) {
+            let (key, value) = pair
+                .split_once('=')
+                .ok_or_else(|| anyhow!("missing value for parameter {pair:?}"))?;
+            if args.insert(key, value).is_some() {
+                bail!("parameter {key:?} given more than once");
+            }
+        }
+        Ok(Self(args))
+    }
+
+    fn take<T>(&mut self, key: &str) -> Result<Option<T>, Error>
+    where
+        T: FromStr,
+        T::Err: Display,
+    {
+        self.0
+            .remove(key)
+            .map(|value| {
+                value
+                    .parse()
+                    .map_err(|e| anyhow!("invalid value {value:?} for parameter {key:?}: {e}"))
+            })
+            .transpose()
+    }
+
+    fn require<T>(&mut self, key: &str) -> Result<T, Error>
+    where
+        T: FromStr,
+        T::Err: Display,
+    {
+        self.take(key)?
+            .ok_or_else(|| anyhow!("missing parameter {key:?}"))
+    }
+
+    /// Fails if any parameter was not consumed.
+    fn finish(self) -> Result<(), Error> {
+        match self.0.keys().next() {
+            Some(key) => Err(anyhow!("unknown parameter {key:?}")),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn distribution_with_bounds() {
+        let params = OpenParams::parse("uniform?min=10&max=20").unwrap();
+        assert!(matches!(params.source, Source::Distribution(DistributionConfig::Uniform)));
+        assert_eq!(params.min, Some(10));
+        assert_eq!(params.max, Some(20));
+        assert_eq!(params.seed, None);
+    }
+
+    #[test]
+    fn std_dev_alias() {
+        for spec in ["normal?mean=5&sd=2", "normal?mean=5&std_dev=2"] {
+            let params = OpenParams::parse(spec).unwrap();
+            assert!(matches!(
+                params.source,
+                Source::Distribution(DistributionConfig::Normal { mean, std_dev })
+                    if mean == 5.0 && std_dev == 2.0
+            ));
+        }
+        assert!(OpenParams::parse("normal?mean=5").unwrap_err().to_string().contains("std_dev"));
+    }
+
+    #[test]
+    fn replay() {
+        let params = OpenParams::parse("replay:///abs/path?speed=10").unwrap();
+        assert!(matches!(
+            params.source,
+            Source::Replay { ref path, speed } if path == "/abs/path" && speed == 10.0
+        ));
+
+        let params = OpenParams::parse("replay:///abs/path").unwrap();
+        assert!(matches!(params.source, Source::Replay { speed, .. } if speed == 1.0));
+    }
+
+    #[test]
+    fn overrides_only() {
+        let params = OpenParams::parse("?seed=7").unwrap();
+        assert!(matches!(params.source, Source::Configured));
+        assert_eq!(params.seed, Some(7));
+
+        let params = OpenParams::parse("").unwrap();
+        assert!(matches!(params.source, Source::Configured));
+        assert_eq!(params.seed, None);
+    }
+
+    #[test]
+    fn duplicate_key() {
+        let err = OpenParams::parse("uniform?min=1&min=2").unwrap_err();
+        assert!(err.to_string().contains("more than once"));
+    }
+
+    #[test]
+    fn unknown_key() {
+        let err = OpenParams::parse("uniform?mean=1").unwrap_err();
+        assert!(err.to_string().contains("unknown parameter \"mean\""));
+        let err = OpenParams::parse("uniform?min").unwrap_err();
+        assert!(err.to_string().contains("missing value"));
+        let err = OpenParams::parse("uniform?min=ten").unwrap_err();
+        assert!(err.to_string().contains("invalid value"));
+    }
+
+    #[test]
+    fn unknown_generator() {
+        let err = OpenParams::parse("gamma?shape=2").unwrap_err();
+        assert!(err.to_string().contains("unknown generator \"gamma\""));
+    }
+}
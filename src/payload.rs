@@ -0,0 +1,265 @@
+use falco_plugin::anyhow::{anyhow, bail, Error};
+use std::fmt::{self, Display, Formatter};
+
+/// Version of the payload layout written by this plugin.
+///
+/// Version 1 payloads start with a 12 bytes header, all integers being little endian:
+///
+/// | offset | size | content             |
+/// |--------|------|---------------------|
+/// | 0      | 1    | format version      |
+/// | 1      | 1    | value type          |
+/// | 2      | 2    | stream id           |
+/// | 4      | 8    | sequence number     |
+/// | 12     | ...  | value               |
+///
+/// Numbers take 8 bytes (`f64` values are stored as their IEEE 754 bits), booleans
+/// a single byte and strings as many bytes as their UTF-8 encoding.
+///
+/// Older builds wrote the bare 8 bytes of the value, these legacy payloads are
+/// recognized by their length, shorter than any versioned payload.
+pub const FORMAT_VERSION: u8 = 1;
+
+const HEADER_LEN: usize = 12;
+const LEGACY_LEN: usize = 8;
+
+/// Type of the value carried by an event.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ValueType {
+    U64 = 0,
+    I64 = 1,
+    F64 = 2,
+    Bool = 3,
+    Str = 4,
+}
+
+impl ValueType {
+    pub fn name(self) -> &'static str {
+        match self {
+            ValueType::U64 => "u64",
+            ValueType::I64 => "i64",
+            ValueType::F64 => "f64",
+            ValueType::Bool => "bool",
+            ValueType::Str => "string",
+        }
+    }
+}
+
+impl TryFrom<u8> for ValueType {
+    type Error = Error;
+
+    fn try_from(value: u8) -> Result<Self, Error> {
+        match value {
+            0 => Ok(ValueType::U64),
+            1 => Ok(ValueType::I64),
+            2 => Ok(ValueType::F64),
+            3 => Ok(ValueType::Bool),
+            4 => Ok(ValueType::Str),
+            other => Err(anyhow!("unknown value type {other}")),
+        }
+    }
+}
+
+/// The value carried by an event.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    U64(u64),
+    I64(i64),
+    F64(f64),
+    Bool(bool),
+    Str(String),
+}
+
+impl Value {
+    pub fn value_type(&self) -> ValueType {
+        match self {
+            Value::U64(_) => ValueType::U64,
+            Value::I64(_) => ValueType::I64,
+            Value::F64(_) => ValueType::F64,
+            Value::Bool(_) => ValueType::Bool,
+            Value::Str(_) => ValueType::Str,
+        }
+    }
+
+    fn encode(&self, buf: &mut Vec<u8>) {
+        match self {
+            Value::U64(num) => buf.extend_from_slice(&num.to_le_bytes()),
+            Value::I64(num) => buf.extend_from_slice(&num.to_le_bytes()),
+            Value::F64(num) => buf.extend_from_slice(&num.to_bits().to_le_bytes()),
+            Value::Bool(b) => buf.push(u8::from(*b)),
+            Value::Str(s) => buf.extend_from_slice(s.as_bytes()),
+        }
+    }
+
+    fn decode(value_type: ValueType, data: &[u8]) -> Result<Self, Error> {
+        let value = match value_type {
+            ValueType::U64 => Value::U64(u64::from_le_bytes(fixed(value_type, data)?)),
+            ValueType::I64 => Value::I64(i64::from_le_bytes(fixed(value_type, data)?)),
+            ValueType::F64 => Value::F64(f64::from_bits(u64::from_le_bytes(fixed(value_type, data)?))),
+            ValueType::Bool => match data {
+                [0] => Value::Bool(false),
+                [1] => Value::Bool(true),
+                _ => bail!("invalid bool value {data:?}"),
+            },
+            ValueType::Str => Value::Str(String::from_utf8(data.to_vec())?),
+        };
+        Ok(value)
+    }
+}
+
+/// Checks the length of a fixed size value.
+fn fixed<const N: usize>(value_type: ValueType, data: &[u8]) -> Result<[u8; N], Error> {
+    data.try_into()
+        .map_err(|_| anyhow!("invalid {} value length: {} bytes", value_type.name(), data.len()))
+}
+
+impl Display for Value {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::U64(num) => write!(f, "{num}"),
+            Value::I64(num) => write!(f, "{num}"),
+            Value::F64(num) => write!(f, "{num}"),
+            Value::Bool(b) => write!(f, "{b}"),
+            Value::Str(s) => write!(f, "{s}"),
+        }
+    }
+}
+
+/// A decoded event payload.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Payload {
+    /// Stream the event belongs to
+    pub stream: u16,
+
+    /// Position of the event in its stream, unknown for legacy payloads
+    pub seq: Option<u64>,
+
+    pub value: Value,
+}
+
+impl Payload {
+    pub fn new(stream: u16, seq: u64, value: Value) -> Self {
+        Self {
+            stream,
+            seq: Some(seq),
+            value,
+        }
+    }
+
+    /// Serializes the payload with the current format version.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(HEADER_LEN + 8);
+        buf.push(FORMAT_VERSION);
+        buf.push(self.value.value_type() as u8);
+        buf.extend_from_slice(&self.stream.to_le_bytes());
+        buf.extend_from_slice(&self.seq.unwrap_or_default().to_le_bytes());
+        self.value.encode(&mut buf);
+        buf
+    }
+
+    /// Deserializes a payload of any supported format version.
+    pub fn decode(buf: &[u8]) -> Result<Self, Error> {
+        if buf.len() == LEGACY_LEN {
+            return Ok(Self {
+                stream: 0,
+                seq: None,
+                value: Value::U64(u64::from_le_bytes(buf.try_into()?)),
+            });
+        }
+        if buf.len() < HEADER_LEN {
+            bail!("payload too short: {} bytes", buf.len());
+        }
+
+        let version = buf[0];
+        if version != FORMAT_VERSION {
+            bail!("unsupported payload format version {version}");
+        }
+        let value_type = ValueType::try_from(buf[1])?;
+        let stream = u16::from_le_bytes(buf[2..4].try_into()?);
+        let seq = u64::from_le_bytes(buf[4..12].try_into()?);
+        let value = Value::decode(value_type, &buf[HEADER_LEN..])?;
+
+        Ok(Self {
+            stream,
+            seq: Some(seq),
+            value,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let values = [
+            Value::U64(u64::MAX),
+            Value::I64(-42),
+            Value::F64(-0.5),
+            Value::Bool(false),
+            Value::Bool(true),
+            Value::Str(String::from("héllo")),
+            Value::Str(String::new()),
+        ];
+        for (seq, value) in values.into_iter().enumerate() {
+            let payload = Payload::new(7, seq as u64, value);
+            assert_eq!(Payload::decode(&payload.encode()).unwrap(), payload);
+        }
+    }
+
+    #[test]
+    fn header_layout() {
+        let buf = Payload::new(0x0102, 3, Value::Bool(true)).encode();
+        assert_eq!(buf, [FORMAT_VERSION, 3, 0x02, 0x01, 3, 0, 0, 0, 0, 0, 0, 0, 1]);
+    }
+
+    #[test]
+    fn legacy() {
+        let payload = Payload::decode(&1234u64.to_le_bytes()).unwrap();
+        assert_eq!(payload.stream, 0);
+        assert_eq!(payload.seq, None);
+        assert_eq!(payload.value, Value::U64(1234));
+    }
+
+    #[test]
+    fn too_short() {
+        for len in (0..HEADER_LEN).filter(|&len| len != LEGACY_LEN) {
+            let err = Payload::decode(&vec![FORMAT_VERSION; len]).unwrap_err();
+            assert!(err.to_string().contains("too short"));
+        }
+    }
+
+    #[test]
+    fn unknown_version() {
+        let mut buf = Payload::new(0, 0, Value::U64(1)).encode();
+        buf[0] = FORMAT_VERSION + 1;
+        let err = Payload::decode(&buf).unwrap_err();
+        assert!(err.to_string().contains("unsupported payload format version"));
+    }
+
+    #[test]
+    fn unknown_value_type() {
+        let mut buf = Payload::new(0, 0, Value::U64(1)).encode();
+        buf[1] = 5;
+        assert!(Payload::decode(&buf).unwrap_err().to_string().contains("unknown value type 5"));
+    }
+
+    #[test]
+    fn invalid_value() {
+        let mut buf = Payload::new(0, 0, Value::Bool(true)).encode();
+        buf[HEADER_LEN] = 2;
+        assert!(Payload::decode(&buf).unwrap_err().to_string().contains("invalid bool value"));
+        buf.push(0);
+        assert!(Payload::decode(&buf).unwrap_err().to_string().contains("invalid bool value"));
+
+        let mut buf = Payload::new(0, 0, Value::U64(1)).encode();
+        buf.pop();
+        let err = Payload::decode(&buf).unwrap_err();
+        assert!(err.to_string().contains("invalid u64 value length"));
+
+        let mut buf = Payload::new(0, 0, Value::Str(String::from("a"))).encode();
+        buf[HEADER_LEN] = 0xff;
+        Payload::decode(&buf).unwrap_err();
+    }
+}
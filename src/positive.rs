@@ -0,0 +1,20 @@
+use falco_plugin::anyhow::{anyhow, Error};
+use falco_plugin::schemars::gen::SchemaGenerator;
+use falco_plugin::schemars::schema::Schema;
+
+/// Schema of a number that must be greater than 0. `#[schemars(range(min = 0))]`
+/// would accept 0 itself, so this one sets `exclusiveMinimum` instead.
+pub fn schema(gen: &mut SchemaGenerator) -> Schema {
+    let mut schema = gen.subschema_for::<f64>().into_object();
+    schema.number().exclusive_minimum = Some(0.0);
+    schema.into()
+}
+
+/// Checks that the `name` parameter is a finite number greater than 0.
+pub fn check(name: &str, value: f64) -> Result<(), Error> {
+    if value.is_finite() && value > 0.0 {
+        Ok(())
+    } else {
+        Err(anyhow!("{name} must be a positive number, got {value}"))
+    }
+}
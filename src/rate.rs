@@ -0,0 +1,165 @@
+use crate::positive;
+use falco_plugin::anyhow::{anyhow, Error};
+use falco_plugin::schemars::JsonSchema;
+use falco_plugin::serde::{Deserialize, Serialize};
+use rand::Rng;
+use rand_distr::{Distribution, Exp};
+
+/// How often events are generated.
+#[derive(JsonSchema, Deserialize, Serialize, Clone, Debug, PartialEq)]
+#[schemars(crate = "falco_plugin::schemars")]
+#[serde(crate = "falco_plugin::serde", tag = "mode", rename_all = "snake_case", deny_unknown_fields)]
+pub enum RateConfig {
+    /// Evenly spaced events.
+    Fixed {
+        #[schemars(schema_with = "positive::schema")]
+        events_per_second: f64,
+    },
+    /// Exponentially distributed inter-arrival times averaging `mean_rate` events per second.
+    Poisson {
+        #[schemars(schema_with = "positive::schema")]
+        mean_rate: f64,
+    },
+    /// Events are generated as fast as they are consumed.
+    Unlimited,
+}
+
+impl Default for RateConfig {
+    fn default() -> Self {
+        RateConfig::Fixed { events_per_second: 2.0 }
+    }
+}
+
+/// What to do with the events that should have been generated while
+/// the consumer was not polling.
+#[derive(JsonSchema, Deserialize, Serialize, Clone, Copy, Debug, Default, PartialEq)]
+#[schemars(crate = "falco_plugin::schemars")]
+#[serde(crate = "falco_plugin::serde", rename_all = "snake_case")]
+pub enum CatchUp {
+    /// Generate every missed event at once.
+    #[default]
+    Burst,
+    /// Drop the missed events, keeping the original schedule.
+    Skip,
+    /// Drop the missed events and restart the schedule from the current time.
+    Drift,
+}
+
+impl CatchUp {
+    /// Moves an overdue `next_ts` according to the policy, given the current time
+    /// and the average interval between events. Returns the number of dropped ticks.
+    pub fn reschedule(self, next_ts: &mut u64, now: u64, interval: u64) -> u64 {
+        if interval == 0 || *next_ts >= now {
+            return 0;
+        }
+
+        // The most recent overdue tick is still generated, only the ones before it are missed
+        let missed = (now - *next_ts) / interval;
+        match self {
+            CatchUp::Burst => 0,
+            CatchUp::Skip => {
+                *next_ts += missed * interval;
+                missed
+            }
+            CatchUp::Drift => {
+                *next_ts = now;
+                missed
+            }
+        }
+    }
+}
+
+/// Produces the time between two consecutive events.
+pub enum Rate {
+    Fixed(u64),
+    Poisson(Exp<f64>, u64),
+    Unlimited,
+}
+
+impl Rate {
+    pub fn new(config: &RateConfig) -> Result<Self, Error> {
+        let rate = match *config {
+            RateConfig::Fixed { events_per_second } => {
+                positive::check("events_per_second", events_per_second)?;
+                Rate::Fixed(secs_to_nanos(1.0 / events_per_second))
+            }
+            RateConfig::Poisson { mean_rate } => {
+                positive::check("mean_rate", mean_rate)?;
+                Rate::Poisson(
+                    Exp::new(mean_rate).map_err(|e| anyhow!("invalid mean_rate: {e}"))?,
+                    secs_to_nanos(1.0 / mean_rate),
+                )
+            }
+            RateConfig::Unlimited => Rate::Unlimited,
+        };
+        Ok(rate)
+    }
+
+    /// Returns the delay until the next event, in nanoseconds.
+    pub fn next_interval<R: Rng + ?Sized>(&self, rng: &mut R) -> u64 {
+        match self {
+            Rate::Fixed(interval) => *interval,
+            Rate::Poisson(exp, _) => secs_to_nanos(exp.sample(rng)),
+            Rate::Unlimited => 0,
+        }
+    }
+
+    /// Returns the average delay between events, in nanoseconds.
+    pub fn mean_interval(&self) -> u64 {
+        match self {
+            Rate::Fixed(interval) | Rate::Poisson(_, interval) => *interval,
+            Rate::Unlimited => 0,
+        }
+    }
+}
+
+fn secs_to_nanos(secs: f64) -> u64 {
+    // `as` saturates, so absurdly long intervals become u64::MAX
+    (secs * 1e9) as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Reschedules an event due at 100 with a 10 interval, at time `now`.
+    fn reschedule(catch_up: CatchUp, now: u64) -> (u64, u64) {
+        let mut next_ts = 100;
+        let missed = catch_up.reschedule(&mut next_ts, now, 10);
+        (next_ts, missed)
+    }
+
+    #[test]
+    fn burst() {
+        assert_eq!(reschedule(CatchUp::Burst, 135), (100, 0));
+    }
+
+    #[test]
+    fn skip() {
+        // The ticks at 100, 110 and 120 are missed, the one at 130 is still generated
+        assert_eq!(reschedule(CatchUp::Skip, 135), (130, 3));
+        assert_eq!(reschedule(CatchUp::Skip, 130), (130, 3));
+        assert_eq!(reschedule(CatchUp::Skip, 105), (100, 0));
+    }
+
+    #[test]
+    fn drift() {
+        assert_eq!(reschedule(CatchUp::Drift, 135), (135, 3));
+        assert_eq!(reschedule(CatchUp::Drift, 105), (105, 0));
+    }
+
+    #[test]
+    fn on_schedule() {
+        for catch_up in [CatchUp::Burst, CatchUp::Skip, CatchUp::Drift] {
+            assert_eq!(reschedule(catch_up, 100), (100, 0));
+            assert_eq!(reschedule(catch_up, 50), (100, 0));
+        }
+    }
+
+    #[test]
+    fn unlimited() {
+        let mut next_ts = 100;
+        assert_eq!(CatchUp::Skip.reschedule(&mut next_ts, 200, 0), 0);
+        assert_eq!(next_ts, 100);
+    }
+}
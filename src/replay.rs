@@ -0,0 +1,174 @@
+use falco_plugin::anyhow::{anyhow, bail, Context, Error};
+use serde_json::Value;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Lines};
+
+/// A value read back from a replay file, with its timestamp if one was recorded.
+#[derive(Debug)]
+struct Record {
+    value: u64,
+    ts: Option<u64>,
+}
+
+/// Reads values back from a JSON lines file. Each line holds either a bare
+/// number or an object with a `value` and an optional `ts`, in nanoseconds.
+pub struct Replay {
+    path: String,
+    lines: Lines<BufReader<File>>,
+    line_no: usize,
+    speed: f64,
+    current: Option<Record>,
+}
+
+impl Replay {
+    pub fn open(path: &str, speed: f64) -> Result<Self, Error> {
+        if !(speed.is_finite() && speed > 0.0) {
+            bail!("replay speed must be a positive number, got {speed}");
+        }
+        let file = File::open(path).with_context(|| format!("cannot open replay file {path}"))?;
+        let mut replay = Self {
+            path: path.to_string(),
+            lines: BufReader::new(file).lines(),
+            line_no: 0,
+            speed,
+            current: None,
+        };
+        replay.current = replay.read_record()?;
+        Ok(replay)
+    }
+
+    /// The value due next, or `None` once the whole file has been replayed.
+    pub fn current(&self) -> Option<u64> {
+        self.current.as_ref().map(|record| record.value)
+    }
+
+    /// Moves on to the following record. When both records carry a timestamp,
+    /// returns the delay between them in nanoseconds, divided by the replay speed.
+    pub fn advance(&mut self) -> Result<Option<u64>, Error> {
+        let next = self.read_record()?;
+        let prev_ts = self.current.as_ref().and_then(|record| record.ts);
+        let next_ts = next.as_ref().and_then(|record| record.ts);
+        self.current = next;
+
+        Ok(match (prev_ts, next_ts) {
+            (Some(prev), Some(next)) => Some((next.saturating_sub(prev) as f64 / self.speed) as u64),
+            _ => None,
+        })
+    }
+
+    fn read_record(&mut self) -> Result<Option<Record>, Error> {
+        for line in self.lines.by_ref() {
+            self.line_no += 1;
+            let line = line?;
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            let record = parse_record(line)
+                .with_context(|| format!("{}:{}: invalid replay record", self.path, self.line_no))?;
+            return Ok(Some(record));
+        }
+        Ok(None)
+    }
+}
+
+fn parse_record(line: &str) -> Result<Record, Error> {
+    match serde_json::from_str::<Value>(line)? {
+        Value::Number(value) => Ok(Record {
+            value: value
+                .as_u64()
+                .ok_or_else(|| anyhow!("{value} is not an unsigned integer"))?,
+            ts: None,
+        }),
+        Value::Object(record) => Ok(Record {
+            value: record
+                .get("value")
+                .and_then(Value::as_u64)
+                .ok_or_else(|| anyhow!("missing unsigned integer `value`"))?,
+            ts: record
+                .get("ts")
+                .map(|ts| ts.as_u64().ok_or_else(|| anyhow!("`ts` is not an unsigned integer")))
+                .transpose()?,
+        }),
+        _ => Err(anyhow!("expected a number or an object")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    /// Writes `contents` to a replay file unique to the calling test.
+    fn replay_file(name: &str, contents: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("replay-{}-{name}.jsonl", std::process::id()));
+        std::fs::write(&path, contents).unwrap();
+        path
+    }
+
+    fn open(name: &str, contents: &str, speed: f64) -> Result<Replay, Error> {
+        let path = replay_file(name, contents);
+        let replay = Replay::open(path.to_str().unwrap(), speed);
+        std::fs::remove_file(path).unwrap();
+        replay
+    }
+
+    #[test]
+    fn records() {
+        let record = parse_record("42").unwrap();
+        assert_eq!((record.value, record.ts), (42, None));
+        let record = parse_record(r#"{"value": 42, "ts": 1000}"#).unwrap();
+        assert_eq!((record.value, record.ts), (42, Some(1000)));
+        let record = parse_record(r#"{"value": 42}"#).unwrap();
+        assert_eq!((record.value, record.ts), (42, None));
+    }
+
+    #[test]
+    fn invalid_records() {
+        for line in ["-1", "1.5", r#""42""#, "[42]", "{", r#"{"ts": 1000}"#, r#"{"value": -1}"#] {
+            assert!(parse_record(line).is_err(), "{line}");
+        }
+        let err = parse_record(r#"{"value": 1, "ts": "now"}"#).unwrap_err();
+        assert!(err.to_string().contains("`ts`"));
+    }
+
+    #[test]
+    fn blank_lines() {
+        let mut replay = open("blank_lines", "\n1\n  \n\n2\n", 1.0).unwrap();
+        assert_eq!(replay.current(), Some(1));
+        replay.advance().unwrap();
+        assert_eq!(replay.current(), Some(2));
+        replay.advance().unwrap();
+        assert_eq!(replay.current(), None);
+    }
+
+    #[test]
+    fn error_line_number() {
+        let err = open("error_line_number", "1\n\nnope\n", 1.0)
+            .and_then(|mut replay| replay.advance())
+            .unwrap_err();
+        assert!(format!("{err:#}").contains(":3: invalid replay record"), "{err:#}");
+    }
+
+    #[test]
+    fn gaps() {
+        let contents = r#"{"value": 1, "ts": 1000}
+{"value": 2, "ts": 5000}
+{"value": 3}
+{"value": 4, "ts": 9000}
+"#;
+        let mut replay = open("gaps", contents, 4.0).unwrap();
+        assert_eq!(replay.advance().unwrap(), Some(1000));
+        // Only one of the two consecutive records has a timestamp
+        assert_eq!(replay.advance().unwrap(), None);
+        assert_eq!(replay.advance().unwrap(), None);
+        assert_eq!(replay.current(), Some(4));
+    }
+
+    #[test]
+    fn invalid_speed() {
+        for speed in [0.0, -1.0, f64::NAN, f64::INFINITY] {
+            assert!(open("invalid_speed", "1\n", speed).is_err());
+        }
+    }
+}
@@ -0,0 +1,135 @@
+use falco_plugin::schemars::JsonSchema;
+use falco_plugin::serde::{Deserialize, Serialize};
+use rand::rngs::StdRng;
+use rand::{RngCore, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+use rand_pcg::Pcg64;
+use rand_xoshiro::Xoshiro256PlusPlus;
+
+/// The pseudo random number generator algorithm.
+#[derive(JsonSchema, Deserialize, Serialize, Clone, Copy, Debug, Default, PartialEq)]
+#[schemars(crate = "falco_plugin::schemars")]
+#[serde(crate = "falco_plugin::serde")]
+pub enum Algorithm {
+    /// ChaCha with 20 rounds, the default.
+    #[default]
+    #[serde(rename = "chacha20")]
+    ChaCha20,
+    /// PCG XSL RR 128/64.
+    #[serde(rename = "pcg64")]
+    Pcg64,
+    /// Xoshiro256++.
+    #[serde(rename = "xoshiro256plusplus")]
+    Xoshiro256PlusPlus,
+    /// The `rand` crate standard generator. Its algorithm is not guaranteed
+    /// to stay the same across `rand` releases, so sequences may change
+    /// after a dependency upgrade.
+    #[serde(rename = "std")]
+    StdRng,
+}
+
+/// Random number generator backed by one of the supported [`Algorithm`]s.
+pub enum GenRng {
+    ChaCha20(ChaCha20Rng),
+    Pcg64(Pcg64),
+    Xoshiro256PlusPlus(Xoshiro256PlusPlus),
+    StdRng(StdRng),
+}
+
+impl GenRng {
+    /// Creates a generator. The same `seed` always yields the same sequence,
+    /// without one the generator is seeded from the OS entropy source.
+    pub fn new(algorithm: Algorithm, seed: Option<u64>) -> Self {
+        match algorithm {
+            Algorithm::ChaCha20 => GenRng::ChaCha20(seeded(seed)),
+            Algorithm::Pcg64 => GenRng::Pcg64(seeded(seed)),
+            Algorithm::Xoshiro256PlusPlus => GenRng::Xoshiro256PlusPlus(seeded(seed)),
+            Algorithm::StdRng => GenRng::StdRng(seeded(seed)),
+        }
+    }
+}
+
+/// Derives the seed of a stream from the configured seed. The first stream uses it as is,
+/// so that a single stream keeps its sequence, and the others take the successive outputs
+/// of a SplitMix64 generator seeded with it: consecutive seeds or indexes give unrelated
+/// seeds, instead of streams sharing their sequence with the neighbouring seed.
+pub fn stream_seed(seed: u64, index: u64) -> u64 {
+    if index == 0 {
+        return seed;
+    }
+    splitmix64(seed.wrapping_add((index - 1).wrapping_mul(SPLITMIX64_GAMMA)))
+}
+
+const SPLITMIX64_GAMMA: u64 = 0x9e37_79b9_7f4a_7c15;
+
+/// Advances a SplitMix64 generator in state `state` and returns its output.
+fn splitmix64(state: u64) -> u64 {
+    let mut z = state.wrapping_add(SPLITMIX64_GAMMA);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+fn seeded<R: SeedableRng>(seed: Option<u64>) -> R {
+    match seed {
+        Some(seed) => R::seed_from_u64(seed),
+        None => R::from_entropy(),
+    }
+}
+
+impl RngCore for GenRng {
+    fn next_u32(&mut self) -> u32 {
+        match self {
+            GenRng::ChaCha20(rng) => rng.next_u32(),
+            GenRng::Pcg64(rng) => rng.next_u32(),
+            GenRng::Xoshiro256PlusPlus(rng) => rng.next_u32(),
+            GenRng::StdRng(rng) => rng.next_u32(),
+        }
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        match self {
+            GenRng::ChaCha20(rng) => rng.next_u64(),
+            GenRng::Pcg64(rng) => rng.next_u64(),
+            GenRng::Xoshiro256PlusPlus(rng) => rng.next_u64(),
+            GenRng::StdRng(rng) => rng.next_u64(),
+        }
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        match self {
+            GenRng::ChaCha20(rng) => rng.fill_bytes(dest),
+            GenRng::Pcg64(rng) => rng.fill_bytes(dest),
+            GenRng::Xoshiro256PlusPlus(rng) => rng.fill_bytes(dest),
+            GenRng::StdRng(rng) => rng.fill_bytes(dest),
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        match self {
+            GenRng::ChaCha20(rng) => rng.try_fill_bytes(dest),
+            GenRng::Pcg64(rng) => rng.try_fill_bytes(dest),
+            GenRng::Xoshiro256PlusPlus(rng) => rng.try_fill_bytes(dest),
+            GenRng::StdRng(rng) => rng.try_fill_bytes(dest),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splitmix64_outputs() {
+        assert_eq!(stream_seed(0, 1), 0xe220_a839_7b1d_cdaf);
+        assert_eq!(stream_seed(0, 2), 0x6e78_9e6a_a1b9_65f4);
+    }
+
+    #[test]
+    fn stream_seeds() {
+        assert_eq!(stream_seed(42, 0), 42);
+        let seeds = [stream_seed(42, 1), stream_seed(42, 2), stream_seed(43, 1)];
+        assert!(seeds.iter().all(|&seed| seed != 42 && seed != 43 && seed != 44));
+        assert!(seeds[0] != seeds[1] && seeds[0] != seeds[2] && seeds[1] != seeds[2]);
+    }
+}
@@ -0,0 +1,122 @@
+/// Number of items kept by the top level compactor. The rank error stays around
+/// 1.7 / `K`, i.e. below 1%, and the sketch holds about 3 * `K` values at most.
+const K: usize = 200;
+
+/// Number of items kept by the lowest level compactors.
+const MIN_CAPACITY: usize = 8;
+
+/// Streaming quantile estimator with bounded memory: a KLL sketch.
+///
+/// Values go into compactors stacked in levels, a value at level `h` standing for
+/// 2^`h` of the original ones. When a level gets full, it's sorted and every other
+/// value moves up a level, the rest being discarded. Higher levels get more room,
+/// lower ones geometrically less.
+pub struct QuantileSketch {
+    levels: Vec<Vec<u64>>,
+    /// Xorshift state picking which half of a compacted level is kept. Always
+    /// keeping the same half, or alternating, biases the estimates.
+    coin: u64,
+}
+
+impl Default for QuantileSketch {
+    fn default() -> Self {
+        Self {
+            levels: vec![Vec::new()],
+            coin: 0x9e37_79b9_7f4a_7c15,
+        }
+    }
+}
+
+impl QuantileSketch {
+    pub fn insert(&mut self, value: u64) {
+        self.levels[0].push(value);
+        while let Some(level) = (0..self.levels.len()).find(|&h| self.levels[h].len() >= self.capacity(h)) {
+            self.compact(level);
+        }
+    }
+
+    fn capacity(&self, level: usize) -> usize {
+        let depth = (self.levels.len() - 1 - level) as i32;
+        ((K as f64 * (2.0f64 / 3.0).powi(depth)).ceil() as usize).max(MIN_CAPACITY)
+    }
+
+    fn compact(&mut self, level: usize) {
+        if level + 1 == self.levels.len() {
+            self.levels.push(Vec::new());
+        }
+        let mut items = std::mem::take(&mut self.levels[level]);
+        items.sort_unstable();
+        // An odd value out stays at its level
+        if items.len() % 2 == 1 {
+            self.levels[level].extend(items.pop());
+        }
+        self.coin ^= self.coin << 13;
+        self.coin ^= self.coin >> 7;
+        self.coin ^= self.coin << 17;
+        let offset = (self.coin & 1) as usize;
+        let promoted = items.into_iter().skip(offset).step_by(2);
+        self.levels[level + 1].extend(promoted);
+    }
+
+    /// Estimates the value below which a `q` fraction of the inserted values fall,
+    /// `q` going from 0 (the minimum) to 1 (the maximum).
+    pub fn quantile(&self, q: f64) -> Option<u64> {
+        let mut items: Vec<(u64, u64)> = self
+            .levels
+            .iter()
+            .enumerate()
+            .flat_map(|(h, level)| level.iter().map(move |&value| (value, 1u64 << h)))
+            .collect();
+        items.sort_unstable();
+
+        let total: u64 = items.iter().map(|&(_, weight)| weight).sum();
+        let target = q.clamp(0.0, 1.0) * total as f64;
+        let mut seen = 0;
+        for &(value, weight) in &items {
+            seen += weight;
+            if seen as f64 >= target {
+                return Some(value);
+            }
+        }
+        items.last().map(|&(value, _)| value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const N: u64 = 1_000_000;
+
+    /// Checks the estimates against the exact quantiles of `0..N`, inserted in any order.
+    fn check(values: impl Iterator<Item = u64>) {
+        let mut sketch = QuantileSketch::default();
+        values.for_each(|value| sketch.insert(value));
+
+        for q in [0.01, 0.25, 0.5, 0.9, 0.99] {
+            let estimate = sketch.quantile(q).unwrap();
+            let rank_error = (estimate as f64 / N as f64 - q).abs();
+            assert!(rank_error < 0.01, "quantile {q}: {estimate}, rank error {rank_error}");
+        }
+
+        let items: usize = sketch.levels.iter().map(Vec::len).sum();
+        assert!(items < 3 * K, "{items} items kept");
+    }
+
+    #[test]
+    fn shuffled() {
+        // 7919 is coprime with N, so this visits every value once
+        check((0..N).map(|i| i * 7919 % N));
+    }
+
+    #[test]
+    fn sorted() {
+        check(0..N);
+        check((0..N).rev());
+    }
+
+    #[test]
+    fn empty() {
+        assert_eq!(QuantileSketch::default().quantile(0.5), None);
+    }
+}
@@ -0,0 +1,342 @@
+use crate::bucket::Buckets;
+use crate::config::{Settings, StreamSettings};
+use crate::distribution::DistributionConfig;
+use crate::payload::{Payload, Value};
+use crate::prob::gamma_q;
+use crate::sketch::QuantileSketch;
+use crate::window::SlidingWindow;
+use std::collections::{BTreeMap, VecDeque};
+use std::ops::RangeBounds;
+
+/// What the plugin keeps track of for every stream, fed by `parse_event`.
+#[derive(Default)]
+pub struct StreamStats {
+    /// All numbers generated with how many times each one occurred,
+    /// empty unless `exact_histogram` is set
+    pub histogram: BTreeMap<u64, u64>,
+
+    /// Number of u64 values
+    pub total: u64,
+
+    /// Mean and variance of all the u64 values
+    pub moments: Moments,
+
+    /// Z-score of the last value against the values before it
+    pub zscore: Option<f64>,
+
+    /// Sequence number of the last event, unknown for legacy payloads
+    pub last_seq: Option<u64>,
+
+    /// Number of sequence numbers skipped right before the last event
+    pub seq_gap: u64,
+
+    /// The most recent u64 values
+    pub window: SlidingWindow,
+
+    /// Approximate distribution of all the u64 values
+    pub sketch: QuantileSketch,
+
+    /// The last u64 values, the most recent one at the back
+    pub recent: VecDeque<u64>,
+
+    /// Number of u64 values in each histogram bucket
+    pub bucket_counts: Vec<u64>,
+
+    /// Number of u64 values in each chi-square bin of the stream, empty unless
+    /// `exact_histogram` is set
+    pub chi_square_counts: Vec<u64>,
+}
+
+impl StreamStats {
+    /// Accounts for a parsed event, stamped with `ts`. Only u64 values are counted.
+    pub fn record(&mut self, payload: &Payload, ts: u64, settings: &Settings) {
+        if let Some(seq) = payload.seq {
+            // A sequence going backwards means a new capture, which isn't a gap
+            self.seq_gap = match self.last_seq {
+                Some(last) => seq.saturating_sub(last).saturating_sub(1),
+                None => 0,
+            };
+            self.last_seq = Some(seq);
+        }
+        if let Value::U64(num) = payload.value {
+            if settings.exact_histogram {
+                *self.histogram.entry(num).or_insert(0) += 1;
+                if let Some(stream) = settings.streams.get(usize::from(payload.stream)) {
+                    let bins = &stream.chi_square_bins;
+                    if let Some(index) = bins.index(num) {
+                        self.chi_square_counts.resize(bins.count(), 0);
+                        self.chi_square_counts[index] += 1;
+                    }
+                }
+            }
+            self.total += 1;
+            self.zscore = self.moments.zscore(num as f64);
+            self.moments.push(num as f64);
+            self.window.push(settings.window, ts, num);
+            self.sketch.insert(num);
+
+            if let Some(buckets) = &settings.buckets {
+                self.bucket_counts.resize(buckets.count(), 0);
+                self.bucket_counts[buckets.index(num)] += 1;
+            }
+
+            // The current value is kept on top of the `max_lag` previous ones
+            self.recent.push_back(num);
+            while self.recent.len() > settings.max_lag.saturating_add(1) {
+                self.recent.pop_front();
+            }
+        }
+    }
+
+    /// Counts the values of the histogram again into new `buckets`. Without the exact
+    /// histogram, the counts start over.
+    pub fn recount_buckets(&mut self, buckets: Option<&Buckets>) {
+        self.bucket_counts.clear();
+        if let Some(buckets) = buckets {
+            self.bucket_counts.resize(buckets.count(), 0);
+            for (&num, &count) in &self.histogram {
+                self.bucket_counts[buckets.index(num)] += count;
+            }
+        }
+    }
+
+    /// Counts the values of the histogram again into the chi-square bins of `stream`,
+    /// or starts over without a stream.
+    pub fn recount_chi_square(&mut self, stream: Option<&StreamSettings>) {
+        self.chi_square_counts.clear();
+        if let Some(bins) = stream.map(|stream| &stream.chi_square_bins) {
+            self.chi_square_counts.resize(bins.count(), 0);
+            for (&num, &count) in &self.histogram {
+                if let Some(index) = bins.index(num) {
+                    self.chi_square_counts[index] += count;
+                }
+            }
+        }
+    }
+
+    /// Returns how many u64 values fall in `range`.
+    pub fn count_range(&self, range: impl RangeBounds<u64>) -> u64 {
+        self.histogram.range(range).map(|(_, count)| count).sum()
+    }
+
+    /// Returns the observed frequency of `num` among the u64 values.
+    pub fn frequency(&self, num: u64) -> f64 {
+        match self.histogram.get(&num) {
+            Some(&count) => count as f64 / self.total as f64,
+            None => 0.0,
+        }
+    }
+
+    /// Returns the value `n` events back, 0 being the last one.
+    pub fn lag(&self, n: usize) -> Option<u64> {
+        self.recent.iter().rev().nth(n).copied()
+    }
+}
+
+/// Number of bins the chi-square test starts from, before merging the sparse ones.
+const CHI_SQUARE_BINS: u64 = 64;
+
+/// Smallest expected count of a chi-square bin for the test to be meaningful.
+const MIN_EXPECTED: f64 = 5.0;
+
+/// The bins the chi-square test starts from: equal width bins spanning the bounds of a
+/// stream, with the probability of each one under its distribution. They only depend on
+/// the stream settings, so they are computed once instead of for every test.
+#[derive(PartialEq)]
+pub struct ChiSquareBins(Vec<Bin>);
+
+#[derive(PartialEq)]
+struct Bin {
+    from: u64,
+    to: u64,
+    probability: f64,
+}
+
+impl ChiSquareBins {
+    pub fn new(distribution: &DistributionConfig, min: u64, max: u64) -> Self {
+        let width = ((max - min) / CHI_SQUARE_BINS).saturating_add(1);
+        let mut bins = Vec::new();
+        let mut from = min;
+        loop {
+            let to = from.saturating_add(width - 1).min(max);
+            bins.push(Bin {
+                from,
+                to,
+                probability: distribution.range_probability(from, to, min, max),
+            });
+            if to == max {
+                break;
+            }
+            from = to + 1;
+        }
+        Self(bins)
+    }
+
+    /// Number of bins.
+    pub fn count(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Returns the index of the bin `num` falls in, if it's within the bounds. Values out
+    /// of bounds were generated before a configuration change.
+    pub fn index(&self, num: u64) -> Option<usize> {
+        let index = self.0.partition_point(|bin| bin.to < num);
+        self.0.get(index).filter(|bin| bin.from <= num).map(|_| index)
+    }
+}
+
+/// Result of a chi-square goodness of fit test.
+pub struct ChiSquare {
+    pub statistic: f64,
+    pub pvalue: f64,
+}
+
+impl StreamStats {
+    /// Pearson's chi-square test of the u64 values against the distribution of `stream`,
+    /// over equal width bins spanning its bounds. Bins expecting fewer than 5 values are
+    /// merged with the next ones, and there's no result until two bins are left.
+    pub fn chi_square(&self, stream: &StreamSettings) -> Option<ChiSquare> {
+        let n = self.chi_square_counts.iter().sum::<u64>() as f64;
+
+        // Observed and expected counts of each bin
+        let mut bins: Vec<(f64, f64)> = Vec::new();
+        let mut pending = (0.0, 0.0);
+        for (index, bin) in stream.chi_square_bins.0.iter().enumerate() {
+            pending.0 += self.chi_square_counts.get(index).copied().unwrap_or(0) as f64;
+            pending.1 += n * bin.probability;
+            if pending.1 >= MIN_EXPECTED {
+                bins.push(std::mem::take(&mut pending));
+            }
+        }
+        // The sparse leftover goes with the last bin
+        let last = bins.last_mut()?;
+        last.0 += pending.0;
+        last.1 += pending.1;
+        if bins.len() < 2 {
+            return None;
+        }
+
+        let statistic = bins
+            .iter()
+            .map(|&(observed, expected)| (observed - expected).powi(2) / expected)
+            .sum::<f64>();
+        let dof = (bins.len() - 1) as f64;
+        Some(ChiSquare {
+            statistic,
+            pvalue: gamma_q(dof / 2.0, statistic / 2.0),
+        })
+    }
+}
+
+/// Running mean and variance, updated with Welford's algorithm.
+#[derive(Default)]
+pub struct Moments {
+    count: u64,
+    mean: f64,
+    /// Sum of the squared differences from the mean
+    m2: f64,
+}
+
+impl Moments {
+    pub fn push(&mut self, x: f64) {
+        self.count += 1;
+        let delta = x - self.mean;
+        self.mean += delta / self.count as f64;
+        self.m2 += delta * (x - self.mean);
+    }
+
+    /// Number of population standard deviations between `x` and the mean,
+    /// unknown until the values vary.
+    pub fn zscore(&self, x: f64) -> Option<f64> {
+        let stddev = (self.m2 / self.count as f64).sqrt();
+        (self.count >= 2 && stddev > 0.0).then(|| (x - self.mean) / stddev)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::distribution::Sampler;
+    use crate::rate::Rate;
+    use crate::value::ValueTypeConfig;
+
+    fn uniform(min: u64, max: u64) -> StreamSettings {
+        StreamSettings {
+            name: String::new(),
+            min,
+            max,
+            distribution: DistributionConfig::Uniform,
+            sampler: Sampler::Uniform,
+            value_type: ValueTypeConfig::default(),
+            chi_square_bins: ChiSquareBins::new(&DistributionConfig::Uniform, min, max),
+            rate: Rate::Unlimited,
+        }
+    }
+
+    #[test]
+    fn chi_square_bins_span_bounds() {
+        for (min, max) in [(0, 0), (10, 73), (0, 999), (5, u64::MAX)] {
+            let bins = ChiSquareBins::new(&DistributionConfig::Uniform, min, max).0;
+            assert!(bins.len() as u64 <= CHI_SQUARE_BINS + 1);
+            assert_eq!(bins.first().unwrap().from, min);
+            assert_eq!(bins.last().unwrap().to, max);
+            assert!(bins.windows(2).all(|pair| pair[1].from == pair[0].to + 1));
+
+            let total: f64 = bins.iter().map(|bin| bin.probability).sum();
+            assert!((total - 1.0).abs() < 1e-9, "{min}..={max}: {total}");
+        }
+    }
+    #[test]
+    fn chi_square_bins_index() {
+        let bins = ChiSquareBins::new(&DistributionConfig::Uniform, 10, 73);
+        assert_eq!(bins.count(), 64);
+        assert_eq!(bins.index(9), None);
+        assert_eq!(bins.index(10), Some(0));
+        assert_eq!(bins.index(73), Some(63));
+        assert_eq!(bins.index(74), None);
+
+        // 16 values wide, the last bin being shorter
+        let bins = ChiSquareBins::new(&DistributionConfig::Uniform, 0, 999);
+        assert_eq!(bins.count(), 63);
+        assert_eq!(bins.index(15), Some(0));
+        assert_eq!(bins.index(16), Some(1));
+        assert_eq!(bins.index(999), Some(62));
+    }
+
+    #[test]
+    fn chi_square() {
+        let mut stats = StreamStats {
+            histogram: (0..100).map(|num| (num, 10)).collect(),
+            ..Default::default()
+        };
+        // Out of bounds, left out of the test
+        stats.histogram.insert(500, 7);
+
+        stats.recount_chi_square(Some(&uniform(0, 99)));
+        assert_eq!(stats.chi_square_counts.iter().sum::<u64>(), 1000);
+        let result = stats.chi_square(&uniform(0, 99)).unwrap();
+        assert_eq!(result.statistic, 0.0);
+        assert_eq!(result.pvalue, 1.0);
+
+        // Every value in the lower half, twice as many as expected
+        stats.histogram = (0..50).map(|num| (num, 20)).collect();
+        stats.recount_chi_square(Some(&uniform(0, 99)));
+        let result = stats.chi_square(&uniform(0, 99)).unwrap();
+        assert!((result.statistic - 1000.0).abs() < 1e-9);
+        assert!(result.pvalue < 1e-6);
+
+        // Narrower bounds only keep the values within them
+        stats.recount_chi_square(Some(&uniform(0, 49)));
+        assert_eq!(stats.chi_square_counts.iter().sum::<u64>(), 1000);
+        assert_eq!(stats.chi_square(&uniform(0, 49)).unwrap().statistic, 0.0);
+
+        // Not enough values to expect 5 in two bins
+        stats.histogram = BTreeMap::from([(0, 9)]);
+        stats.recount_chi_square(Some(&uniform(0, 49)));
+        assert!(stats.chi_square(&uniform(0, 49)).is_none());
+
+        stats.recount_chi_square(None);
+        assert!(stats.chi_square_counts.is_empty());
+        assert!(stats.chi_square(&uniform(0, 49)).is_none());
+    }
+}
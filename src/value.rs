@@ -0,0 +1,143 @@
+use crate::distribution::Sampler;
+use crate::payload::Value;
+use falco_plugin::anyhow::{bail, Error};
+use falco_plugin::schemars::JsonSchema;
+use falco_plugin::serde::{Deserialize, Serialize};
+use rand::Rng;
+
+/// The type of the generated values, and how they are derived from the
+/// values drawn from the distribution.
+#[derive(JsonSchema, Deserialize, Serialize, Clone, Debug, Default, PartialEq)]
+#[schemars(crate = "falco_plugin::schemars")]
+#[serde(crate = "falco_plugin::serde", tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum ValueTypeConfig {
+    /// Unsigned integers, as drawn.
+    #[default]
+    U64,
+    /// Signed integers: the drawn value shifted by `offset`.
+    I64 {
+        #[serde(default)]
+        offset: i64,
+    },
+    /// Floating point numbers: the drawn value, not rounded, shifted by `offset`.
+    F64 {
+        #[serde(default)]
+        offset: f64,
+    },
+    /// Booleans: true when the drawn value is at least `threshold`,
+    /// the middle of the bounds when omitted.
+    Bool { threshold: Option<f64> },
+    /// Strings: the drawn value picks one of `values`, or is
+    /// rendered in decimal when the list is empty.
+    String {
+        #[serde(default)]
+        values: Vec<String>,
+    },
+}
+
+impl ValueTypeConfig {
+    pub fn validate(&self) -> Result<(), Error> {
+        match self {
+            ValueTypeConfig::F64 { offset } if !offset.is_finite() => {
+                bail!("`offset` must be a finite number, got {offset}")
+            }
+            ValueTypeConfig::Bool {
+                threshold: Some(threshold),
+            } if !threshold.is_finite() => {
+                bail!("`threshold` must be a finite number, got {threshold}")
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Draws a value of this type from `sampler`, within `min..=max`.
+    pub fn draw<R: Rng + ?Sized>(&self, sampler: &Sampler, rng: &mut R, min: u64, max: u64) -> Value {
+        match self {
+            ValueTypeConfig::U64 => Value::U64(sampler.sample(rng, min, max)),
+            ValueTypeConfig::I64 { offset } => {
+                let num = sampler.sample(rng, min, max);
+                Value::I64(i64::try_from(num).unwrap_or(i64::MAX).saturating_add(*offset))
+            }
+            ValueTypeConfig::F64 { offset } => Value::F64(sampler.sample_f64(rng, min, max) + offset),
+            ValueTypeConfig::Bool { threshold } => {
+                let threshold = threshold.unwrap_or((min as f64 + max as f64) / 2.0);
+                Value::Bool(sampler.sample(rng, min, max) as f64 >= threshold)
+            }
+            ValueTypeConfig::String { values } => {
+                let num = sampler.sample(rng, min, max);
+                if values.is_empty() {
+                    Value::Str(num.to_string())
+                } else {
+                    let index = (num - min) % values.len() as u64;
+                    Value::Str(values[index as usize].clone())
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::distribution::DistributionConfig;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    /// Draws a value of `value_type` from a distribution always drawing `num`, within `min..=max`.
+    fn draw(value_type: &ValueTypeConfig, num: u64, min: u64, max: u64) -> Value {
+        let sampler = Sampler::new(&DistributionConfig::Binomial { n: num, p: 1.0 }).unwrap();
+        value_type.draw(&sampler, &mut StdRng::seed_from_u64(0), min, max)
+    }
+
+    #[test]
+    fn i64_values() {
+        let i64_type = |offset| ValueTypeConfig::I64 { offset };
+        assert_eq!(draw(&i64_type(-20), 10, 0, 100), Value::I64(-10));
+        // Values above i64::MAX are clamped before the offset is applied
+        assert_eq!(draw(&i64_type(0), u64::MAX, 0, u64::MAX), Value::I64(i64::MAX));
+        assert_eq!(draw(&i64_type(-5), u64::MAX, 0, u64::MAX), Value::I64(i64::MAX - 5));
+        assert_eq!(draw(&i64_type(1), i64::MAX as u64, 0, u64::MAX), Value::I64(i64::MAX));
+    }
+
+    #[test]
+    fn f64_values() {
+        let f64_type = ValueTypeConfig::F64 { offset: 0.5 };
+        assert_eq!(draw(&f64_type, 10, 0, 100), Value::F64(10.5));
+        assert_eq!(draw(&f64_type, 1000, 0, 100), Value::F64(100.5));
+    }
+
+    #[test]
+    fn bool_threshold() {
+        // The default threshold is the middle of the bounds, 15.5 here
+        let bool_type = ValueTypeConfig::Bool { threshold: None };
+        assert_eq!(draw(&bool_type, 15, 10, 21), Value::Bool(false));
+        assert_eq!(draw(&bool_type, 16, 10, 21), Value::Bool(true));
+        assert_eq!(draw(&bool_type, 15, 10, 20), Value::Bool(true));
+
+        let bool_type = ValueTypeConfig::Bool { threshold: Some(12.0) };
+        assert_eq!(draw(&bool_type, 11, 10, 20), Value::Bool(false));
+        assert_eq!(draw(&bool_type, 12, 10, 20), Value::Bool(true));
+    }
+
+    #[test]
+    fn string_values() {
+        let values = ["a", "b", "c"].map(String::from).to_vec();
+        let string_type = ValueTypeConfig::String { values };
+        let pick = |num| draw(&string_type, num, 10, 20);
+        assert_eq!(pick(10), Value::Str(String::from("a")));
+        assert_eq!(pick(12), Value::Str(String::from("c")));
+        assert_eq!(pick(14), Value::Str(String::from("b")));
+        // Clamped to 20, the 11th value of the bounds
+        assert_eq!(pick(30), Value::Str(String::from("b")));
+
+        let string_type = ValueTypeConfig::String { values: Vec::new() };
+        assert_eq!(draw(&string_type, 42, 0, 100), Value::Str(String::from("42")));
+    }
+
+    #[test]
+    fn validation() {
+        assert!(ValueTypeConfig::F64 { offset: f64::NAN }.validate().is_err());
+        assert!(ValueTypeConfig::Bool { threshold: Some(f64::INFINITY) }.validate().is_err());
+        assert!(ValueTypeConfig::Bool { threshold: None }.validate().is_ok());
+    }
+}
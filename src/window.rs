@@ -0,0 +1,268 @@
+use crate::prob::normal_sf;
+use falco_plugin::anyhow::{bail, Error};
+use falco_plugin::schemars::JsonSchema;
+use falco_plugin::serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
+
+/// Which recent events the sliding window statistics are computed over.
+#[derive(JsonSchema, Deserialize, Serialize, Clone, Debug, PartialEq)]
+#[schemars(crate = "falco_plugin::schemars")]
+#[serde(crate = "falco_plugin::serde", tag = "mode", rename_all = "snake_case", deny_unknown_fields)]
+pub enum WindowConfig {
+    /// The last `size` events.
+    Events {
+        #[schemars(range(min = 1))]
+        size: usize,
+    },
+    /// The events of the last `secs` seconds, in event time.
+    Duration {
+        #[schemars(range(min = 1))]
+        secs: u64,
+    },
+}
+
+impl Default for WindowConfig {
+    fn default() -> Self {
+        WindowConfig::Events { size: 100 }
+    }
+}
+
+/// How many events a window keeps.
+#[derive(Clone, Copy)]
+pub enum Window {
+    Events(usize),
+    /// Length of the window, in nanoseconds
+    Duration(u64),
+}
+
+impl Window {
+    pub fn new(config: &WindowConfig) -> Result<Self, Error> {
+        let window = match *config {
+            WindowConfig::Events { size: 0 } => bail!("the window `size` must be greater than 0"),
+            WindowConfig::Events { size } => Window::Events(size),
+            WindowConfig::Duration { secs: 0 } => bail!("the window `secs` must be greater than 0"),
+            WindowConfig::Duration { secs } => Window::Duration(secs.saturating_mul(1_000_000_000)),
+        };
+        Ok(window)
+    }
+}
+
+/// The most recent values of a stream, with their timestamps.
+#[derive(Default)]
+pub struct SlidingWindow {
+    entries: VecDeque<(u64, u64)>,
+}
+
+impl SlidingWindow {
+    /// Adds a value and evicts the ones that fell out of the window.
+    pub fn push(&mut self, window: Window, ts: u64, value: u64) {
+        self.entries.push_back((ts, value));
+        match window {
+            Window::Events(size) => {
+                while self.entries.len() > size {
+                    self.entries.pop_front();
+                }
+            }
+            Window::Duration(length) => {
+                let start = ts.saturating_sub(length);
+                while self.entries.front().is_some_and(|&(ts, _)| ts <= start) {
+                    self.entries.pop_front();
+                }
+            }
+        }
+    }
+
+    fn values(&self) -> impl Iterator<Item = u64> + '_ {
+        self.entries.iter().map(|&(_, value)| value)
+    }
+
+    pub fn mean(&self) -> Option<f64> {
+        if self.entries.is_empty() {
+            return None;
+        }
+        let sum: f64 = self.values().map(|value| value as f64).sum();
+        Some(sum / self.entries.len() as f64)
+    }
+
+    /// The population standard deviation.
+    pub fn stddev(&self) -> Option<f64> {
+        let mean = self.mean()?;
+        let sum: f64 = self.values().map(|value| (value as f64 - mean).powi(2)).sum();
+        Some((sum / self.entries.len() as f64).sqrt())
+    }
+
+    pub fn min(&self) -> Option<u64> {
+        self.values().min()
+    }
+
+    pub fn max(&self) -> Option<u64> {
+        self.values().max()
+    }
+
+    /// The middle value, or the mean of the two middle ones.
+    pub fn median(&self) -> Option<f64> {
+        let mut values: Vec<u64> = self.values().collect();
+        values.sort_unstable();
+        let mid = values.len() / 2;
+        match values.len() {
+            0 => None,
+            len if len % 2 == 1 => Some(values[mid] as f64),
+            _ => Some((values[mid - 1] as f64 + values[mid] as f64) / 2.0),
+        }
+    }
+
+    /// Wald-Wolfowitz runs test on the values above and below the median: the two-sided
+    /// p-value of the number of runs, low when the values trend or alternate too much.
+    pub fn runs_pvalue(&self) -> Option<f64> {
+        let median = self.median()?;
+        // Values equal to the median belong to neither side
+        let sides: Vec<bool> = self
+            .values()
+            .map(|value| value as f64)
+            .filter(|&value| value != median)
+            .map(|value| value > median)
+            .collect();
+        if sides.len() < 2 {
+            return None;
+        }
+        let runs = 1 + sides.windows(2).filter(|pair| pair[0] != pair[1]).count();
+
+        let n = sides.len() as f64;
+        let above = sides.iter().filter(|&&above| above).count() as f64;
+        let below = n - above;
+        let mean = 2.0 * above * below / n + 1.0;
+        let variance = 2.0 * above * below * (2.0 * above * below - n) / (n * n * (n - 1.0));
+        (variance > 0.0).then(|| 2.0 * normal_sf(((runs as f64 - mean) / variance.sqrt()).abs()))
+    }
+
+    /// Shannon entropy of the values, in bits.
+    pub fn entropy(&self) -> Option<f64> {
+        if self.entries.is_empty() {
+            return None;
+        }
+        let mut counts = BTreeMap::new();
+        for value in self.values() {
+            *counts.entry(value).or_insert(0u64) += 1;
+        }
+        let n = self.entries.len() as f64;
+        Some(
+            counts
+                .values()
+                .map(|&count| {
+                    let p = count as f64 / n;
+                    -p * p.log2()
+                })
+                .sum(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn window(values: &[u64]) -> SlidingWindow {
+        let mut window = SlidingWindow::default();
+        for (ts, &value) in values.iter().enumerate() {
+            window.push(Window::Events(values.len()), ts as u64, value);
+        }
+        window
+    }
+
+    #[test]
+    fn empty() {
+        let window = SlidingWindow::default();
+        assert_eq!(window.mean(), None);
+        assert_eq!(window.stddev(), None);
+        assert_eq!(window.min(), None);
+        assert_eq!(window.max(), None);
+        assert_eq!(window.median(), None);
+    }
+
+    #[test]
+    fn statistics() {
+        let window = window(&[2, 4, 4, 4, 5, 5, 7, 9]);
+        assert_eq!(window.mean(), Some(5.0));
+        // The population standard deviation, the sample one being about 2.14
+        assert_eq!(window.stddev(), Some(2.0));
+        assert_eq!(window.min(), Some(2));
+        assert_eq!(window.max(), Some(9));
+    }
+
+    #[test]
+    fn median() {
+        assert_eq!(window(&[7]).median(), Some(7.0));
+        assert_eq!(window(&[9, 1, 5]).median(), Some(5.0));
+        assert_eq!(window(&[9, 1, 4, 5]).median(), Some(4.5));
+        assert_eq!(window(&[u64::MAX, u64::MAX]).median(), Some(u64::MAX as f64));
+    }
+
+    #[test]
+    fn events_eviction() {
+        let mut window = SlidingWindow::default();
+        for value in 1..=5 {
+            window.push(Window::Events(3), 0, value);
+        }
+        assert_eq!(window.values().collect::<Vec<_>>(), [3, 4, 5]);
+    }
+
+    #[test]
+    fn duration_eviction() {
+        let mut window = SlidingWindow::default();
+        window.push(Window::Duration(10), 100, 1);
+        window.push(Window::Duration(10), 105, 2);
+        window.push(Window::Duration(10), 109, 3);
+        assert_eq!(window.values().collect::<Vec<_>>(), [1, 2, 3]);
+        // The first value is now exactly 10 old, which is out of the window
+        window.push(Window::Duration(10), 110, 4);
+        assert_eq!(window.values().collect::<Vec<_>>(), [2, 3, 4]);
+        window.push(Window::Duration(10), 200, 5);
+        assert_eq!(window.values().collect::<Vec<_>>(), [5]);
+    }
+
+    #[test]
+    fn config() {
+        assert!(Window::new(&WindowConfig::Events { size: 0 }).is_err());
+        assert!(Window::new(&WindowConfig::Duration { secs: 0 }).is_err());
+        assert!(matches!(
+            Window::new(&WindowConfig::Duration { secs: 2 }),
+            Ok(Window::Duration(2_000_000_000))
+        ));
+    }
+    #[test]
+    fn runs() {
+        // 10 values, 5 on each side of the median: 6 runs expected, with a variance of 20/9
+        let pvalue = |runs: f64| 2.0 * normal_sf((runs - 6.0).abs() / (20.0f64 / 9.0).sqrt());
+        let close = |window: SlidingWindow, runs: f64| {
+            let actual = window.runs_pvalue().unwrap();
+            assert!((actual - pvalue(runs)).abs() < 1e-12, "{actual} for {runs} runs");
+        };
+        // Trending, alternating and neither
+        close(window(&[1, 2, 3, 4, 5, 6, 7, 8, 9, 10]), 2.0);
+        close(window(&[1, 10, 2, 9, 3, 8, 4, 7, 5, 6]), 10.0);
+        close(window(&[1, 2, 10, 9, 3, 8, 4, 5, 7, 6]), 6.0);
+        assert!(window(&[1, 2, 3, 4, 5, 6, 7, 8, 9, 10]).runs_pvalue().unwrap() < 0.01);
+        assert!(window(&[1, 2, 10, 9, 3, 8, 4, 5, 7, 6]).runs_pvalue().unwrap() > 0.99);
+        // Values equal to the median are left out
+        close(window(&[1, 2, 3, 4, 5, 50, 6, 7, 8, 9, 10]), 2.0);
+    }
+
+    #[test]
+    fn runs_not_enough_values() {
+        assert_eq!(SlidingWindow::default().runs_pvalue(), None);
+        assert_eq!(window(&[3]).runs_pvalue(), None);
+        // Only the 9 isn't the median
+        assert_eq!(window(&[5, 5, 9]).runs_pvalue(), None);
+        assert_eq!(window(&[4, 4, 4, 4]).runs_pvalue(), None);
+        // Two values on opposite sides can only make two runs
+        assert_eq!(window(&[1, 5, 9]).runs_pvalue(), None);
+    }
+
+    #[test]
+    fn entropy() {
+        assert_eq!(SlidingWindow::default().entropy(), None);
+        assert_eq!(window(&[7, 7, 7]).entropy(), Some(0.0));
+        assert_eq!(window(&[1, 2, 3, 4]).entropy(), Some(2.0));
+        assert_eq!(window(&[1, 1, 2, 3]).entropy(), Some(1.5));
+    }
+}